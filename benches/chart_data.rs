@@ -0,0 +1,54 @@
+//! Benchmarks parsing chart data into owned and borrowed types.
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::hint::black_box;
+
+use bms_table::{BmsTableData, BmsTableDataRef};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use serde_json::json;
+
+/// Build chart data JSON with `count` charts shaped like those of large public tables.
+fn chart_data_json(count: usize) -> String {
+    let charts: Vec<_> = (0..count)
+        .map(|i| {
+            json!({
+                "level": (i % 25).to_string(),
+                "id": i,
+                "md5": format!("{i:032x}"),
+                "sha256": format!("{i:064x}"),
+                "title": format!("Song title number {i} [Another]"),
+                "artist": format!("Artist {i} / obj: Someone"),
+                "url": format!("https://example.com/songs/{i}.zip"),
+                "url_diff": format!("https://example.com/diffs/{i}.zip"),
+                "name_diff": "SP ANOTHER",
+                "comment": "",
+                "lr2_bmsid": i * 7,
+                "state": 0,
+            })
+        })
+        .collect();
+    serde_json::to_string(&charts).unwrap_or_default()
+}
+
+/// Compare owned parsing, borrowed parsing, and borrowed parsing followed by `into_owned`.
+fn bench_chart_data(c: &mut Criterion) {
+    let text = chart_data_json(5000);
+    let mut group = c.benchmark_group("chart_data_5000");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("owned", |b| {
+        b.iter(|| serde_json::from_str::<BmsTableData>(black_box(&text)));
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| BmsTableDataRef::from_json_str(black_box(&text)));
+    });
+    group.bench_function("borrowed_into_owned", |b| {
+        b.iter(|| {
+            BmsTableDataRef::from_json_str(black_box(&text)).map(BmsTableDataRef::into_owned)
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_chart_data);
+criterion_main!(benches);
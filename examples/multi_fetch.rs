@@ -1,7 +1,7 @@
 //! Concurrent multi-table fetching example
 //!
 //! This example demonstrates fetching multiple BMS difficulty tables concurrently and emitting events when each table finishes.
-//! It uses asynchronous concurrency to process multiple tables in parallel for better efficiency.
+//! It uses `Fetcher::fetch_tables`, which yields each table as soon as it is done, with bounded concurrency.
 //!
 //! # Features
 //!
@@ -12,15 +12,18 @@
 #![cfg_attr(not(feature = "reqwest"), allow(unused_imports))]
 
 use anyhow::Result;
-use bms_table::BmsTable;
 #[cfg(feature = "reqwest")]
 use bms_table::fetch::reqwest::Fetcher;
-use std::env;
 #[cfg(feature = "reqwest")]
-use tokio::sync::mpsc;
+use futures_util::StreamExt;
+use std::env;
 #[cfg(feature = "reqwest")]
 use url::Url;
 
+/// Maximum number of tables fetched at the same time
+#[cfg(feature = "reqwest")]
+const CONCURRENCY: usize = 8;
+
 /// Main function
 ///
 /// Demonstrates the full functionality of concurrent fetching across multiple difficulty tables.
@@ -53,57 +56,32 @@ async fn main() -> Result<()> {
     println!("Fetching {url_count} difficulty tables...");
     println!();
 
-    // Create a channel for event handling
-    let (tx, mut rx) = mpsc::channel::<FetchResult>(100);
-
-    // Start the event handler task
-    let event_handler = tokio::spawn(async move {
-        while let Some(result) = rx.recv().await {
-            match result.table {
-                Ok(table) => {
-                    println!(
-                        "{} fetched successfully ({} charts, {} course groups, {} courses)",
-                        result.name,
-                        table.data.charts.len(),
-                        table.header.course.len(),
-                        table.header.course.iter().flatten().count()
-                    );
-                }
-                Err(e) => {
-                    println!("{} fetch failed: {}", result.name, e);
-                }
+    // Handle each table as soon as it is fetched
+    let mut results = fetcher.fetch_tables(urls, CONCURRENCY);
+    while let Some((url, result)) = results.next().await {
+        match result {
+            Ok(fetched) => {
+                let table = fetched.table;
+                println!(
+                    "{} fetched successfully ({} charts, {} course groups, {} courses)",
+                    table.header.name,
+                    table.data.charts.len(),
+                    table.header.course.len(),
+                    table.header.course.iter().flatten().count()
+                );
+            }
+            Err(e) => {
+                println!("{url} fetch failed: {e}");
             }
         }
-    });
-
-    // Fetch all tables concurrently
-    let fetch_tasks: Vec<_> = urls
-        .into_iter()
-        .map(|url| {
-            let tx = tx.clone();
-            let fetcher_cloned = fetcher.clone();
-            tokio::spawn(async move {
-                let result = fetch_single_table(&fetcher_cloned, &url).await;
-                let _ = tx.send(result).await;
-            })
-        })
-        .collect();
-
-    // Wait for all fetch tasks to finish
-    for task in fetch_tasks {
-        let _ = task.await;
     }
 
-    // Close the sender and wait for the event handler to finish
-    drop(tx);
-    let _ = event_handler.await;
-
     // Display summary
     println!();
     println!("Fetch summary:");
-    println!("  Concurrency: {url_count} tables");
-    println!("  Processing: async concurrency");
-    println!("  Event handling: real-time dispatch");
+    println!("  Tables: {url_count}");
+    println!("  Concurrency: {CONCURRENCY} tables at a time");
+    println!("  Event handling: completion order");
 
     Ok(())
 }
@@ -152,33 +130,5 @@ fn table_urls() -> Vec<Url> {
     }
 }
 
-/// Fetch result for a difficulty table
-#[derive(Debug)]
-#[cfg(feature = "reqwest")]
-struct FetchResult {
-    /// Table name
-    name: String,
-    /// Result of fetching the table
-    table: anyhow::Result<BmsTable>,
-}
-
-/// Fetch a single difficulty table
-#[cfg(feature = "reqwest")]
-async fn fetch_single_table(fetcher: &Fetcher, url: &Url) -> FetchResult {
-    match fetcher.fetch_table(url.clone()).await {
-        Ok(fetched) => {
-            let bms_table = fetched.table;
-            FetchResult {
-                name: bms_table.header.name.clone(),
-                table: Ok(bms_table),
-            }
-        }
-        Err(e) => FetchResult {
-            name: url.to_string(),
-            table: Err(e),
-        },
-    }
-}
-
 #[cfg(not(feature = "reqwest"))]
 fn main() {}
@@ -34,17 +34,15 @@ async fn main() -> Result<()> {
             eprintln!("Fetch failed for: {}", url);
             eprintln!("Message: {}", e);
             eprintln!("Causes:");
-            for (i, cause) in e.chain().enumerate() {
-                eprintln!("  [{}] {}", i, cause);
+            let mut cause: Option<&dyn std::error::Error> = Some(&e);
+            let mut i = 0;
+            while let Some(err) = cause {
+                eprintln!("  [{}] {}", i, err);
+                cause = err.source();
+                i += 1;
             }
-            match std::env::var("RUST_BACKTRACE").as_deref() {
-                Ok("1") => {
-                    eprintln!("Backtrace:");
-                    eprintln!("{:?}", e.backtrace());
-                }
-                _ => {
-                    eprintln!("Hint: set RUST_BACKTRACE=1 to print backtrace.");
-                }
+            if e.is_transient() {
+                eprintln!("Hint: this failure looks transient; retrying later may succeed.");
             }
         }
     }
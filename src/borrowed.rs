@@ -0,0 +1,258 @@
+//! Borrowed views of chart data
+//!
+//! Parsing a large table into [`BmsTableData`] allocates every string of every chart. [`BmsTableDataRef`]
+//! parses the same JSON while borrowing from the source text instead: string fields are
+//! [`Cow::Borrowed`] unless they contain escape sequences or were numbers, hashes are decoded into
+//! [`Md5`]/[`Sha256`] directly, and unrecognized fields are kept as raw JSON slices. Call
+//! [`BmsTableDataRef::into_owned`] to get the owned types once the text is no longer available.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use bms_table::BmsTableDataRef;
+//!
+//! let text = r#"[{"level": 12, "title": "Song", "id": 1}, {"level": "!i", "title": "歌"}]"#;
+//! let data = BmsTableDataRef::from_json_str(text).unwrap();
+//! assert!(matches!(data.charts[0].title, Some(Cow::Borrowed("Song"))));
+//! assert_eq!(data.charts[0].level, "12");
+//! assert_eq!(data.charts[1].title.as_deref(), Some("歌"));
+//!
+//! let owned = data.into_owned();
+//! assert_eq!(owned.charts[0].extra["id"], 1);
+//! ```
+#![cfg(feature = "serde")]
+
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
+use serde_json::{Value, value::RawValue};
+
+use crate::{BmsTableData, ChartItem, Md5, Sha256};
+
+/// Chart data borrowing from the JSON text it was parsed from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct BmsTableDataRef<'a> {
+    /// Charts
+    #[serde(borrow)]
+    pub charts: Vec<ChartItemRef<'a>>,
+}
+
+/// Chart data item borrowing from the JSON text it was parsed from.
+///
+/// Fields mirror [`ChartItem`].
+#[derive(Debug, Clone)]
+pub struct ChartItemRef<'a> {
+    /// Difficulty level, e.g. "0"
+    pub level: Cow<'a, str>,
+    /// MD5 hash of the file (an empty or invalid value is treated as missing)
+    pub md5: Option<Md5>,
+    /// SHA256 hash of the file (an empty or invalid value is treated as missing)
+    pub sha256: Option<Sha256>,
+    /// Song title
+    pub title: Option<Cow<'a, str>>,
+    /// Song subtitle
+    pub subtitle: Option<Cow<'a, str>>,
+    /// Artist name
+    pub artist: Option<Cow<'a, str>>,
+    /// Song sub-artist
+    pub subartist: Option<Cow<'a, str>>,
+    /// File download URL
+    pub url: Option<Cow<'a, str>>,
+    /// Differential file download URL (optional)
+    pub url_diff: Option<Cow<'a, str>>,
+    /// Extra data as raw JSON, in document order
+    pub extra: Vec<(Cow<'a, str>, &'a RawValue)>,
+}
+
+impl<'a> BmsTableDataRef<'a> {
+    /// Parse chart data from JSON text, borrowing from it where possible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid chart data JSON.
+    pub fn from_json_str(s: &'a str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+
+    /// Convert into the owned [`BmsTableData`].
+    #[must_use]
+    pub fn into_owned(self) -> BmsTableData {
+        BmsTableData {
+            charts: self
+                .charts
+                .into_iter()
+                .map(ChartItemRef::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl ChartItemRef<'_> {
+    /// Convert into the owned [`ChartItem`].
+    #[must_use]
+    pub fn into_owned(self) -> ChartItem {
+        ChartItem {
+            level: self.level.into_owned(),
+            md5: self.md5,
+            sha256: self.sha256,
+            title: self.title.map(Cow::into_owned),
+            subtitle: self.subtitle.map(Cow::into_owned),
+            artist: self.artist.map(Cow::into_owned),
+            subartist: self.subartist.map(Cow::into_owned),
+            url: self.url.map(Cow::into_owned),
+            url_diff: self.url_diff.map(Cow::into_owned),
+            extra: self
+                .extra
+                .into_iter()
+                .map(|(key, raw)| {
+                    // A `RawValue` always holds valid JSON.
+                    let value = serde_json::from_str(raw.get()).unwrap_or_default();
+                    (key.into_owned(), value)
+                })
+                .collect::<BTreeMap<String, Value>>(),
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ChartItemRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ChartItemRefVisitor)
+    }
+}
+
+/// Visitor for [`ChartItemRef`].
+struct ChartItemRefVisitor;
+
+impl<'de> Visitor<'de> for ChartItemRefVisitor {
+    type Value = ChartItemRef<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a chart object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut item = ChartItemRef {
+            level: Cow::Borrowed(""),
+            md5: None,
+            sha256: None,
+            title: None,
+            subtitle: None,
+            artist: None,
+            subartist: None,
+            url: None,
+            url_diff: None,
+            extra: Vec::new(),
+        };
+        while let Some(CowStr(key)) = map.next_key()? {
+            match key.as_ref() {
+                "level" => item.level = map.next_value::<CowNumStr<'de>>()?.0,
+                "md5" => item.md5 = parse_hash(map.next_value()?),
+                "sha256" => item.sha256 = parse_hash(map.next_value()?),
+                "title" => item.title = opt_str(map.next_value()?),
+                "subtitle" => item.subtitle = opt_str(map.next_value()?),
+                "artist" => item.artist = opt_str(map.next_value()?),
+                "subartist" => item.subartist = opt_str(map.next_value()?),
+                "url" => item.url = opt_str(map.next_value()?),
+                "url_diff" => item.url_diff = opt_str(map.next_value()?),
+                _ => item.extra.push((key, map.next_value()?)),
+            }
+        }
+        Ok(item)
+    }
+}
+
+/// Unwrap an optional borrowed string.
+fn opt_str(value: Option<CowStr<'_>>) -> Option<Cow<'_, str>> {
+    value.map(|s| s.0)
+}
+
+/// Parse an optional hash; `null`, empty and invalid values become `None`.
+fn parse_hash<T: std::str::FromStr>(raw: &RawValue) -> Option<T> {
+    serde_json::from_str::<Cow<'_, str>>(raw.get())
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// A string borrowed from the input when it contains no escape sequences.
+struct CowStr<'a>(Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for CowStr<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(CowStrVisitor).map(CowStr)
+    }
+}
+
+/// Visitor for [`CowStr`].
+struct CowStrVisitor;
+
+impl<'de> Visitor<'de> for CowStrVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v))
+    }
+}
+
+/// Like [`CowStr`], but numbers are also accepted and converted to strings.
+struct CowNumStr<'a>(Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for CowNumStr<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(CowNumStrVisitor)
+            .map(CowNumStr)
+    }
+}
+
+/// Visitor for [`CowNumStr`].
+struct CowNumStrVisitor;
+
+impl<'de> Visitor<'de> for CowNumStrVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or a number")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(crate::de::float_string(v)))
+    }
+}
@@ -1,99 +1,529 @@
 //! Deserialization implementation module
 //!
 //! Centralizes all `Deserialize` implementations and helper raw types here, keeping `lib.rs` focused on type definitions.
+//! The implementations only rely on serde's data model, so tables load from any self-describing format
+//! (YAML, `MessagePack`, CBOR, ...) and not just JSON.
+//!
+//! Also provides a lenient parse mode for chart arrays: invalid entries are skipped and reported as
+//! [`ChartDiagnostic`]s instead of failing the whole document. Invalid entries of the course `md5`/`sha256`
+//! lists are reported the same way.
+//!
+//! Invalid hashes never fail a document: strict parsing reads an invalid `md5`/`sha256` of a chart as `None`
+//! and skips invalid entries of the course hash lists, while lenient parsing also reports them.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::BmsTableData;
+//!
+//! let parsed = BmsTableData::from_json_str_lenient(r#"[{"level": "1"}, null, {"level": true}]"#).unwrap();
+//! assert_eq!(parsed.value.charts.len(), 1);
+//! assert_eq!(parsed.diagnostics.len(), 2);
+//! assert_eq!(parsed.diagnostics[0].index, 1);
+//! ```
 #![cfg(feature = "serde")]
 
-use serde::{Deserialize, Deserializer};
+use serde::{
+    Deserialize, Deserializer,
+    de::{
+        IgnoredAny, MapAccess, SeqAccess, Visitor,
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+    },
+};
 use serde_json::Value;
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::{
+    BmsTableData, BmsTableHeader, ChartItem, CourseConstraint, CourseInfo, KeyMode, Md5, Sha256,
+    Trophy,
+};
+
+/// Result of a lenient parse: the parsed value plus diagnostics for every rejected chart entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lenient<T> {
+    /// Parsed value containing only the valid chart entries.
+    pub value: T,
+    /// Chart entries that were rejected, in document order.
+    pub diagnostics: Vec<ChartDiagnostic>,
+}
+
+/// Where a rejected chart entry was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChartLocation {
+    /// The chart data array.
+    Data,
+    /// The `charts` array of a course in the header.
+    Course {
+        /// Index of the course group (always `0` for a flat `course` array).
+        group: usize,
+        /// Index of the course within its group.
+        course: usize,
+    },
+    /// The `md5` list of a course in the header.
+    CourseMd5 {
+        /// Index of the course group (always `0` for a flat `course` array).
+        group: usize,
+        /// Index of the course within its group.
+        course: usize,
+    },
+    /// The `sha256` list of a course in the header.
+    CourseSha256 {
+        /// Index of the course group (always `0` for a flat `course` array).
+        group: usize,
+        /// Index of the course within its group.
+        course: usize,
+    },
+}
+
+/// A chart entry rejected by lenient parsing, or kept without its invalid hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartDiagnostic {
+    /// Array containing the entry.
+    pub location: ChartLocation,
+    /// Index of the entry within its array.
+    pub index: usize,
+    /// Raw value of the entry as found in the document.
+    pub raw: Value,
+    /// Why the entry was rejected, or which hash was dropped and why.
+    pub reason: String,
+}
+
+impl BmsTableData {
+    /// Parse chart data leniently from a JSON value.
+    ///
+    /// Every entry that deserializes as a [`ChartItem`] is kept; the others (e.g. `null`, arrays,
+    /// a `level` of `true`) are skipped and reported in [`Lenient::diagnostics`]. Kept entries with an
+    /// invalid `md5` or `sha256` are reported as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only when `value` is not an array.
+    pub fn from_json_value_lenient(value: Value) -> serde_json::Result<Lenient<Self>> {
+        let entries = Vec::<Value>::deserialize(value)?;
+        let mut charts = Vec::with_capacity(entries.len());
+        let mut diagnostics = Vec::new();
+        for (index, raw) in entries.into_iter().enumerate() {
+            match ChartItem::deserialize(&raw) {
+                Ok(item) => {
+                    report_invalid_hashes(&raw, ChartLocation::Data, index, &mut diagnostics);
+                    charts.push(item);
+                }
+                Err(e) => diagnostics.push(ChartDiagnostic {
+                    location: ChartLocation::Data,
+                    index,
+                    raw,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        Ok(Lenient {
+            value: Self { charts },
+            diagnostics,
+        })
+    }
 
-use crate::{ChartItem, CourseInfo, Trophy};
+    /// Parse chart data leniently from a JSON string.
+    ///
+    /// See [`BmsTableData::from_json_value_lenient`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid JSON or is not an array.
+    pub fn from_json_str_lenient(s: &str) -> serde_json::Result<Lenient<Self>> {
+        Self::from_json_value_lenient(serde_json::from_str(s)?)
+    }
+}
+
+impl BmsTableHeader {
+    /// Parse a header leniently from a JSON value.
+    ///
+    /// Invalid entries in the `charts` arrays and `md5`/`sha256` lists of courses are skipped and
+    /// reported in [`Lenient::diagnostics`]; everything else is parsed as strictly as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the header itself is invalid after removing the rejected chart entries.
+    pub fn from_json_value_lenient(mut value: Value) -> serde_json::Result<Lenient<Self>> {
+        let mut diagnostics = Vec::new();
+        if let Some(Value::Array(groups)) = value.get_mut("course") {
+            let nested = matches!(groups.first(), Some(Value::Array(_)));
+            let courses: Vec<(usize, usize, &mut Value)> = if nested {
+                groups
+                    .iter_mut()
+                    .enumerate()
+                    .flat_map(|(group, courses)| {
+                        courses
+                            .as_array_mut()
+                            .into_iter()
+                            .flatten()
+                            .enumerate()
+                            .map(move |(course, v)| (group, course, v))
+                    })
+                    .collect()
+            } else {
+                groups
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(course, v)| (0, course, v))
+                    .collect()
+            };
+            for (group, course, course_value) in courses {
+                retain_valid(
+                    course_value,
+                    "charts",
+                    ChartLocation::Course { group, course },
+                    &mut diagnostics,
+                    |raw| {
+                        CourseChart::deserialize(raw)
+                            .map(drop)
+                            .map_err(|e| e.to_string())
+                    },
+                );
+                retain_valid(
+                    course_value,
+                    "md5",
+                    ChartLocation::CourseMd5 { group, course },
+                    &mut diagnostics,
+                    |raw| Md5::deserialize(raw).map(drop).map_err(|e| e.to_string()),
+                );
+                retain_valid(
+                    course_value,
+                    "sha256",
+                    ChartLocation::CourseSha256 { group, course },
+                    &mut diagnostics,
+                    |raw| {
+                        Sha256::deserialize(raw)
+                            .map(drop)
+                            .map_err(|e| e.to_string())
+                    },
+                );
+            }
+        }
+        Ok(Lenient {
+            value: serde_json::from_value(value)?,
+            diagnostics,
+        })
+    }
+
+    /// Parse a header leniently from a JSON string.
+    ///
+    /// See [`BmsTableHeader::from_json_value_lenient`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid JSON or the header itself is invalid.
+    pub fn from_json_str_lenient(s: &str) -> serde_json::Result<Lenient<Self>> {
+        Self::from_json_value_lenient(serde_json::from_str(s)?)
+    }
+}
+
+/// Keep only the entries of the array `course_value[key]` accepted by `check`, reporting the others.
+///
+/// Kept chart objects with an invalid hash are reported too.
+fn retain_valid(
+    course_value: &mut Value,
+    key: &str,
+    location: ChartLocation,
+    diagnostics: &mut Vec<ChartDiagnostic>,
+    check: impl Fn(&Value) -> Result<(), String>,
+) {
+    let Some(Value::Array(entries)) = course_value.get_mut(key) else {
+        return;
+    };
+    for (index, raw) in std::mem::take(entries).into_iter().enumerate() {
+        match check(&raw) {
+            Ok(()) => {
+                report_invalid_hashes(&raw, location, index, diagnostics);
+                entries.push(raw);
+            }
+            Err(reason) => diagnostics.push(ChartDiagnostic {
+                location,
+                index,
+                raw,
+                reason,
+            }),
+        }
+    }
+}
+
+/// Report the `md5` and `sha256` of the chart object `raw` that deserialization reads as `None`.
+fn report_invalid_hashes(
+    raw: &Value,
+    location: ChartLocation,
+    index: usize,
+    diagnostics: &mut Vec<ChartDiagnostic>,
+) {
+    let errors = [
+        ("md5", raw.get("md5").and_then(hash_error::<Md5>)),
+        ("sha256", raw.get("sha256").and_then(hash_error::<Sha256>)),
+    ];
+    for (key, error) in errors {
+        if let Some(error) = error {
+            diagnostics.push(ChartDiagnostic {
+                location,
+                index,
+                raw: raw.clone(),
+                reason: format!("dropped invalid {key}: {error}"),
+            });
+        }
+    }
+}
+
+/// Why `value` is not a hash, or `None` if it is one or counts as missing (`null` or a blank string).
+fn hash_error<T>(value: &Value) -> Option<String>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match value {
+        Value::Null => None,
+        Value::String(s) if s.trim().is_empty() => None,
+        Value::String(s) => s.parse::<T>().err().map(|e| e.to_string()),
+        other => Some(format!("expected a hash string, found {other}")),
+    }
+}
 
 /// Field-level deserialization: supports `course` being `Vec<CourseInfo>` or `Vec<Vec<CourseInfo>>`,
 /// and returns `vec![Vec::new()]` for an empty array to preserve previous behavior.
+///
+/// A missing `course`, or a value that is not an array, yields an empty list.
 pub(crate) fn deserialize_course_groups<'de, D>(
     deserializer: D,
 ) -> Result<Vec<Vec<CourseInfo>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let Some(Value::Array(arr)) = Option::<Value>::deserialize(deserializer)? else {
-        return Ok(Vec::new());
-    };
-    if arr.is_empty() {
-        return Ok(vec![Vec::new()]);
+    deserializer.deserialize_any(CourseGroupsVisitor)
+}
+
+/// Visitor for the `course` field, see [`deserialize_course_groups`].
+struct CourseGroupsVisitor;
+
+impl CourseGroupsVisitor {
+    /// Ignore a value that is not a course array.
+    const fn ignored<E>() -> Result<Vec<Vec<CourseInfo>>, E> {
+        Ok(Vec::new())
+    }
+}
+
+impl<'de> Visitor<'de> for CourseGroupsVisitor {
+    type Value = Vec<Vec<CourseInfo>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array of courses or of course groups")
     }
 
-    if matches!(arr.first(), Some(Value::Array(_))) {
-        serde_json::from_value::<Vec<Vec<CourseInfo>>>(Value::Array(arr))
-            .map_err(serde::de::Error::custom)
-    } else {
-        let inner: Vec<CourseInfo> =
-            serde_json::from_value(Value::Array(arr)).map_err(serde::de::Error::custom)?;
-        Ok(vec![inner])
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let entries = Vec::<CourseEntry>::deserialize(SeqAccessDeserializer::new(seq))?;
+        if entries.is_empty() {
+            return Ok(vec![Vec::new()]);
+        }
+        if matches!(entries.first(), Some(CourseEntry::Group(_))) {
+            entries
+                .into_iter()
+                .map(|entry| match entry {
+                    CourseEntry::Group(group) => Ok(group),
+                    CourseEntry::Course(_) => Err(serde::de::Error::custom(
+                        "expected an array of courses, found a course",
+                    )),
+                })
+                .collect()
+        } else {
+            let inner = entries
+                .into_iter()
+                .map(|entry| match entry {
+                    CourseEntry::Course(course) => Ok(course),
+                    CourseEntry::Group(_) => Err(serde::de::Error::custom(
+                        "expected a course, found an array of courses",
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(vec![inner])
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        IgnoredAny::deserialize(MapAccessDeserializer::new(map))?;
+        Self::ignored()
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+
+    fn visit_bytes<E>(self, _: &[u8]) -> Result<Self::Value, E> {
+        Self::ignored()
+    }
+}
+
+/// An element of the `course` array: a course group (nested form) or a single course (flat form).
+enum CourseEntry {
+    /// A group of courses.
+    Group(Vec<CourseInfo>),
+    /// A single course.
+    Course(CourseInfo),
+}
+
+impl<'de> Deserialize<'de> for CourseEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(CourseEntryVisitor)
+    }
+}
+
+/// Visitor for [`CourseEntry`], dispatching on the shape of the element.
+struct CourseEntryVisitor;
+
+impl<'de> Visitor<'de> for CourseEntryVisitor {
+    type Value = CourseEntry;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a course or an array of courses")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Vec::deserialize(SeqAccessDeserializer::new(seq)).map(CourseEntry::Group)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        CourseInfo::deserialize(MapAccessDeserializer::new(map)).map(CourseEntry::Course)
     }
 }
 
 /// Field-level deserialization: converts `level_order` numbers or strings to strings,
-/// uses `to_string()` for other types, and returns an empty array by default.
+/// uses the JSON text for other types, and returns an empty array by default.
 pub(crate) fn deserialize_level_order<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let values = Option::<Vec<Value>>::deserialize(deserializer)?.unwrap_or_default();
-    Ok(values
-        .into_iter()
-        .map(|v| match v {
-            Value::Number(n) => n.to_string(),
-            Value::String(s) => s,
-            other => other.to_string(),
-        })
-        .collect())
+    let names = Option::<Vec<LevelName>>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(names.into_iter().map(|n| n.0).collect())
+}
+
+/// A `level_order` entry converted to a string.
+struct LevelName(String);
+
+impl<'de> Deserialize<'de> for LevelName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(LevelNameVisitor)
+            .map(LevelName)
+    }
+}
+
+/// Visitor for [`LevelName`]: accepts any value.
+struct LevelNameVisitor;
+
+impl<'de> Visitor<'de> for LevelNameVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a level name")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(float_string(v))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null.to_string())
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null.to_string())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Value::deserialize(SeqAccessDeserializer::new(seq)).map(|v| v.to_string())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        Value::deserialize(MapAccessDeserializer::new(map)).map(|v| v.to_string())
+    }
 }
 
 /// Internal helper type: used to construct `CourseInfo` more simply and handle md5/sha256 lists.
+///
+/// Fields follow the serialization order of [`CourseInfo`], so formats that write structs as arrays round-trip.
 #[derive(Deserialize)]
 struct CourseInfoRaw {
     /// Course name
     name: String,
     /// Constraint list
     #[serde(default)]
-    constraint: Vec<String>,
+    constraint: Vec<CourseConstraint>,
     /// Trophy list
     #[serde(default)]
     trophy: Vec<Trophy>,
-    /// MD5 list converted into chart items
-    #[serde(default, rename = "md5")]
-    md5list: Vec<String>,
-    /// SHA256 list converted into chart items
-    #[serde(default, rename = "sha256")]
-    sha256list: Vec<String>,
-    /// Raw chart objects (filled with default level if missing)
+    /// Chart objects (filled with default level if missing)
     #[serde(default)]
-    charts: Vec<Value>,
+    charts: Vec<CourseChart>,
+    /// MD5 list converted into chart items (invalid entries are skipped)
+    #[serde(default, rename = "md5", deserialize_with = "de_hash_list")]
+    md5list: Vec<Md5>,
+    /// SHA256 list converted into chart items (invalid entries are skipped)
+    #[serde(default, rename = "sha256", deserialize_with = "de_hash_list")]
+    sha256list: Vec<Sha256>,
 }
 
-impl TryFrom<CourseInfoRaw> for CourseInfo {
-    type Error = String;
-
-    fn try_from(raw: CourseInfoRaw) -> Result<Self, Self::Error> {
+impl From<CourseInfoRaw> for CourseInfo {
+    fn from(raw: CourseInfoRaw) -> Self {
         let mut charts: Vec<ChartItem> =
             Vec::with_capacity(raw.charts.len() + raw.md5list.len() + raw.sha256list.len());
 
         // Process charts and fill missing level with "0"
-        for mut chart_value in raw.charts {
-            if chart_value.get("level").is_none() {
-                let obj = chart_value
-                    .as_object()
-                    .ok_or_else(|| "chart_value is not an object".to_string())?
-                    .clone();
-                let mut obj = obj;
-                obj.insert("level".to_string(), Value::String("0".to_string()));
-                chart_value = Value::Object(obj);
-            }
-            let item: ChartItem = serde_json::from_value(chart_value).map_err(|e| e.to_string())?;
-            charts.push(item);
-        }
+        charts.extend(raw.charts.into_iter().map(ChartItem::from));
 
         // md5list -> charts
         charts.extend(raw.md5list.into_iter().map(|md5| ChartItem {
@@ -123,12 +553,32 @@ impl TryFrom<CourseInfoRaw> for CourseInfo {
             extra: BTreeMap::new(),
         }));
 
-        Ok(Self {
+        Self {
             name: raw.name,
             constraint: raw.constraint,
             trophy: raw.trophy,
             charts,
-        })
+        }
+    }
+}
+
+/// A chart object of a course; unlike in chart data, a missing `level` defaults to `"0"`.
+#[derive(Deserialize)]
+struct CourseChart {
+    /// Level, if present
+    #[serde(default, deserialize_with = "de_opt_numstring")]
+    level: Option<String>,
+    /// Remaining chart fields
+    #[serde(flatten)]
+    chart: ChartItem,
+}
+
+impl From<CourseChart> for ChartItem {
+    fn from(course_chart: CourseChart) -> Self {
+        Self {
+            level: course_chart.level.unwrap_or_else(|| "0".to_string()),
+            ..course_chart.chart
+        }
     }
 }
 
@@ -137,28 +587,164 @@ impl<'de> Deserialize<'de> for CourseInfo {
     where
         D: Deserializer<'de>,
     {
-        let raw = CourseInfoRaw::deserialize(deserializer)?;
-        Self::try_from(raw).map_err(serde::de::Error::custom)
+        CourseInfoRaw::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// A string or a number, converted to a string.
+struct NumString(String);
+
+impl<'de> Deserialize<'de> for NumString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(NumStringVisitor)
+            .map(NumString)
     }
 }
 
-/// General helper to deserialize empty strings into `None`-like behavior.
+/// Visitor for [`NumString`].
+struct NumStringVisitor;
+
+impl Visitor<'_> for NumStringVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or a number")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(float_string(v))
+    }
+}
+
+/// Format a float the way JSON writes it, e.g. `1.0` rather than `1`.
+pub(crate) fn float_string(v: f64) -> String {
+    serde_json::Number::from_f64(v).map_or_else(|| v.to_string(), |n| n.to_string())
+}
+
+/// Field-level deserialization: accepts a string or a number and converts it to a string.
 pub(crate) fn de_numstring<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let opt = Option::<Value>::deserialize(deserializer)?;
-    let Some(value) = opt else {
-        return Err(serde::de::Error::custom(
-            "expected string or number, found None",
-        ));
-    };
-    match value {
-        Value::String(s) => Ok(s),
-        Value::Number(n) => Ok(n.to_string()),
-        other => Err(serde::de::Error::custom(format!(
-            "expected string or number, got {}",
-            other
-        ))),
+    NumString::deserialize(deserializer).map(|n| n.0)
+}
+
+/// Optional variant of [`de_numstring`]: missing or `null` values become `None`.
+pub(crate) fn de_opt_numstring<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<NumString>::deserialize(deserializer).map(|n| n.map(|n| n.0))
+}
+
+/// Deserialize an optional key mode; values that are not strings become `None` instead of failing the header.
+pub(crate) fn de_opt_key_mode<'de, D>(deserializer: D) -> Result<Option<KeyMode>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(OptStrVisitor(|s: &str| Some(KeyMode::from(s))))
+}
+
+/// Deserialize an optional hash; missing, `null`, empty and invalid values become `None`.
+pub(crate) fn de_opt_hash<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    OptHash::deserialize(deserializer).map(|hash| hash.0)
+}
+
+/// Deserialize a list of hashes, skipping the entries that are not valid hashes.
+fn de_hash_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    Vec::<OptHash<T>>::deserialize(deserializer)
+        .map(|entries| entries.into_iter().filter_map(|hash| hash.0).collect())
+}
+
+/// A hash, or `None` for any value that is not a valid hash string.
+struct OptHash<T>(Option<T>);
+
+impl<'de, T: FromStr> Deserialize<'de> for OptHash<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(OptStrVisitor(|s: &str| s.parse().ok()))
+            .map(OptHash)
+    }
+}
+
+/// Visitor converting a string with the wrapped function; any other value is skipped and becomes `None`.
+struct OptStrVisitor<F>(F);
+
+impl<'de, T, F: FnOnce(&str) -> Option<T>> Visitor<'de> for OptStrVisitor<F> {
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok((self.0)(v))
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_bytes<E>(self, _: &[u8]) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        IgnoredAny::deserialize(SeqAccessDeserializer::new(seq))?;
+        Ok(None)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        IgnoredAny::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(None)
     }
 }
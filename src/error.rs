@@ -0,0 +1,285 @@
+//! Error types for the fetch and parse pipeline
+//!
+//! Every stage of "web page → header JSON → chart data" reports failures through [`Error`], so callers can
+//! tell a missing bmstable hint apart from invalid JSON or a network failure without matching on messages.
+//! [`Error::is_transient`] tells whether retrying the same request later may succeed.
+//!
+//! The module needs the `serde` feature, which provides the JSON parse variants ([`Error::HeaderJson`],
+//! [`Error::DataJson`], [`Error::TableListJson`] and [`Error::Json`]). The HTML and network variants need the
+//! `scraper` feature; without it, document URLs are plain strings, like [`BmsTableInfo::url`](crate::BmsTableInfo::url).
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(feature = "scraper")]
+//! # fn main() {
+//! use bms_table::{Error, fetch::try_extract_bmstable_from_html};
+//!
+//! let err = try_extract_bmstable_from_html("<html></html>").unwrap_err();
+//! assert!(matches!(err, Error::HeaderNotFound { .. }));
+//! assert!(!err.is_transient());
+//! # }
+//! # #[cfg(not(feature = "scraper"))]
+//! # fn main() {}
+//! ```
+#![cfg(feature = "serde")]
+
+use std::fmt;
+
+#[cfg(feature = "scraper")]
+use crate::fetch::HeaderAttempt;
+
+/// URL of a document involved in a failure.
+#[cfg(feature = "scraper")]
+type Url = url::Url;
+/// URL of a document involved in a failure.
+#[cfg(not(feature = "scraper"))]
+type Url = String;
+
+/// Boxed error used for failures coming from the underlying HTTP stack.
+#[cfg(feature = "scraper")]
+type Source = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Result alias using [`Error`] as the default error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Stage of the fetch pipeline a request belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FetchStage {
+    /// The table web page given by the caller.
+    WebPage,
+    /// The header JSON referenced by the web page.
+    Header,
+    /// The chart data JSON referenced by `data_url` in the header.
+    Data,
+    /// A table list document.
+    TableList,
+}
+
+impl fmt::Display for FetchStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WebPage => "web page",
+            Self::Header => "header json",
+            Self::Data => "data json",
+            Self::TableList => "table list",
+        })
+    }
+}
+
+/// Errors produced while fetching or parsing BMS difficulty tables.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The URL given by the caller could not be parsed.
+    #[cfg(feature = "scraper")]
+    #[error("invalid target url")]
+    InvalidUrl {
+        /// Underlying parse error.
+        #[source]
+        source: Source,
+    },
+    /// A relative URL found in a document could not be resolved against its base.
+    #[cfg(feature = "scraper")]
+    #[error("cannot resolve `{reference}` against {base}")]
+    ResolveUrl {
+        /// Base URL of the document containing the reference.
+        base: Url,
+        /// Reference as written in the document.
+        reference: String,
+        /// Underlying parse error.
+        #[source]
+        source: url::ParseError,
+    },
+    /// The HTTP client could not be built.
+    #[cfg(feature = "scraper")]
+    #[error("failed to build http client")]
+    Client {
+        /// Underlying builder error.
+        #[source]
+        source: Source,
+    },
+    /// The request failed before a complete response body was received.
+    #[cfg(feature = "scraper")]
+    #[error("network error while fetching {stage} from {url}")]
+    Network {
+        /// Stage of the failed request.
+        stage: FetchStage,
+        /// Requested URL.
+        url: Url,
+        /// Whether the failure is worth retrying (timeouts, connection resets, ...).
+        transient: bool,
+        /// Underlying transport error.
+        #[source]
+        source: Source,
+    },
+    /// The server answered with a non-success HTTP status.
+    #[cfg(feature = "scraper")]
+    #[error("{stage} at {url} returned HTTP status {status}")]
+    Status {
+        /// Stage of the failed request.
+        stage: FetchStage,
+        /// Requested URL.
+        url: Url,
+        /// HTTP status code.
+        status: u16,
+    },
+    /// The web page is neither a header JSON nor contains a bmstable header hint.
+    #[cfg(feature = "scraper")]
+    #[error("bmstable field or header JSON hint not found{}", fmt_at(url.as_ref()))]
+    HeaderNotFound {
+        /// URL of the inspected page, when known.
+        url: Option<Url>,
+    },
+    /// The header document is not a valid header JSON.
+    #[error("invalid header json{}", fmt_at(url.as_ref()))]
+    HeaderJson {
+        /// URL of the header document, when known.
+        url: Option<Url>,
+        /// Underlying JSON error.
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The chart data document is not valid chart data JSON.
+    #[error("invalid data json{}", fmt_at(url.as_ref()))]
+    DataJson {
+        /// URL of the data document, when known.
+        url: Option<Url>,
+        /// Underlying JSON error.
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The table list document is not a valid table list.
+    #[error("invalid table list json{}", fmt_at(url.as_ref()))]
+    TableListJson {
+        /// URL of the table list, when known.
+        url: Option<Url>,
+        /// Underlying JSON error.
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A JSON document of no particular kind could not be parsed, e.g. by
+    /// [`parse_json_str_with_repairs`](crate::fetch::parse_json_str_with_repairs).
+    #[error("invalid json{}", fmt_at(url.as_ref()))]
+    Json {
+        /// URL of the document, when known.
+        url: Option<Url>,
+        /// Underlying JSON error.
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The header URL points to another HTML page instead of a header JSON.
+    #[cfg(feature = "scraper")]
+    #[error("cycled header found: {url} is another page instead of a header json")]
+    CycledHeader {
+        /// Header URL that resolved to another page.
+        url: Url,
+    },
+    /// The fetcher is offline and the document is not in its cache.
+    #[cfg(feature = "scraper")]
+    #[error("{stage} at {url} is not cached and the fetcher is offline")]
+    NotCached {
+        /// Stage of the request.
+        stage: FetchStage,
+        /// Requested URL.
+        url: Url,
+    },
+    /// None of the header candidates found in a web page is a valid header JSON.
+    #[cfg(feature = "scraper")]
+    #[error("none of the {} header candidates of {url} is a valid header json", attempts.len())]
+    HeaderCandidates {
+        /// URL of the web page.
+        url: Url,
+        /// Every candidate that was tried, in order, with the reason it was rejected.
+        attempts: Vec<HeaderAttempt>,
+    },
+}
+
+impl Error {
+    /// Whether the failure is transient, i.e. retrying the same request later may succeed.
+    ///
+    /// Timeouts, connection failures, HTTP 408/429 and 5xx responses are transient;
+    /// malformed documents and invalid URLs are permanent. [`Error::HeaderCandidates`] is transient
+    /// when any of its attempts is.
+    #[must_use]
+    // Only the network variants, which need `scraper`, keep it from being `const`.
+    #[cfg_attr(not(feature = "scraper"), allow(clippy::missing_const_for_fn))]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(feature = "scraper")]
+            Self::Network { transient, .. } => *transient,
+            #[cfg(feature = "scraper")]
+            Self::Status { status, .. } => matches!(*status, 408 | 429 | 500..=599),
+            #[cfg(feature = "scraper")]
+            Self::HeaderCandidates { attempts, .. } => {
+                attempts.iter().any(|a| a.error.is_transient())
+            }
+            _ => false,
+        }
+    }
+
+    /// URL of the document involved in the failure, when known.
+    #[must_use]
+    pub const fn url(&self) -> Option<&Url> {
+        match self {
+            #[cfg(feature = "scraper")]
+            Self::ResolveUrl { base: url, .. }
+            | Self::Network { url, .. }
+            | Self::Status { url, .. }
+            | Self::NotCached { url, .. }
+            | Self::CycledHeader { url }
+            | Self::HeaderCandidates { url, .. } => Some(url),
+            #[cfg(feature = "scraper")]
+            Self::HeaderNotFound { url } => url.as_ref(),
+            Self::HeaderJson { url, .. }
+            | Self::DataJson { url, .. }
+            | Self::TableListJson { url, .. }
+            | Self::Json { url, .. } => url.as_ref(),
+            #[cfg(feature = "scraper")]
+            Self::InvalidUrl { .. } | Self::Client { .. } => None,
+        }
+    }
+
+    /// Pipeline stage the failure belongs to, when it is tied to one.
+    #[must_use]
+    pub const fn stage(&self) -> Option<FetchStage> {
+        match self {
+            #[cfg(feature = "scraper")]
+            Self::Network { stage, .. }
+            | Self::Status { stage, .. }
+            | Self::NotCached { stage, .. } => Some(*stage),
+            #[cfg(feature = "scraper")]
+            Self::HeaderNotFound { .. } => Some(FetchStage::WebPage),
+            #[cfg(feature = "scraper")]
+            Self::CycledHeader { .. } | Self::HeaderCandidates { .. } => Some(FetchStage::Header),
+            Self::HeaderJson { .. } => Some(FetchStage::Header),
+            Self::DataJson { .. } => Some(FetchStage::Data),
+            Self::TableListJson { .. } => Some(FetchStage::TableList),
+            Self::Json { .. } => None,
+            #[cfg(feature = "scraper")]
+            Self::InvalidUrl { .. } | Self::ResolveUrl { .. } | Self::Client { .. } => None,
+        }
+    }
+
+    /// Attach the document URL to parse errors that were produced without one.
+    #[cfg(feature = "scraper")]
+    #[must_use]
+    pub(crate) fn at_url(mut self, document_url: &Url) -> Self {
+        match &mut self {
+            Self::HeaderNotFound { url }
+            | Self::HeaderJson { url, .. }
+            | Self::DataJson { url, .. }
+            | Self::TableListJson { url, .. }
+            | Self::Json { url, .. } => {
+                url.get_or_insert_with(|| document_url.clone());
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Format an optional document URL as a message suffix.
+fn fmt_at(url: Option<&Url>) -> String {
+    url.map(|u| format!(" at {u}")).unwrap_or_default()
+}
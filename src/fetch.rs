@@ -1,7 +1,7 @@
 //! Data fetching and HTML parsing helpers
 //!
 //! Provides HTML parsing when the `scraper` feature is enabled, used to extract the header JSON URL from
-//! `<meta name="bmstable" content="...">` in a page.
+//! `<meta name="bmstable" content="...">` in a page, or a header defined inline in a `<script>` block.
 //! Also provides a unified entry to parse a response string into the header JSON or its URL.
 //!
 //! # Examples
@@ -24,15 +24,31 @@
 //! ```
 #![cfg(feature = "scraper")]
 
+pub mod blocking;
+mod cache;
+pub mod encoding;
+pub mod html_table;
+mod js;
 pub mod reqwest;
+mod resolver;
+mod throttle;
 
-use std::future::Future;
+use std::{future::Future, ops::Range};
 
-use anyhow::{Context, Result, anyhow};
 use scraper::{ElementRef, Html, Selector};
-use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
 
-use crate::{BmsTable, BmsTableInfo, BmsTableRaw};
+#[cfg(feature = "reqwest")]
+pub use cache::{CacheEntry, CacheMode, CachePolicy, DiskCache};
+pub use resolver::{HttpRequest, HttpResponse, ResolveStep, TableResolver};
+#[cfg(feature = "reqwest")]
+pub use throttle::RetryPolicy;
+
+use crate::{
+    BmsTable, BmsTableData, BmsTableHeader, BmsTableInfo, BmsTableRaw,
+    error::{Error, FetchStage, Result},
+};
 
 /// Result of fetching a table with its raw JSON strings.
 pub struct FetchedTable {
@@ -40,14 +56,208 @@ pub struct FetchedTable {
     pub table: BmsTable,
     /// Raw JSON strings and resolved URLs.
     pub raw: BmsTableRaw,
+    /// Header candidates of the web page that were tried and rejected before the header was found.
+    pub header_attempts: Vec<HeaderAttempt>,
+    /// Cache validators of each stage, to make the next fetch of the same table conditional.
+    pub validators: TableValidators,
+}
+
+/// HTTP cache validators of one fetched document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    /// URL the document was requested from; the validators are only sent to this URL.
+    pub url: url::Url,
+    /// Value of the `ETag` response header, sent back as `If-None-Match`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` response header, sent back as `If-Modified-Since`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+/// Cache validators of the documents a table was resolved from, see [`FetchedTable::validators`].
+///
+/// When the header or data is defined in the web page itself, its validators are those of the web page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableValidators {
+    /// Validators of the web page.
+    #[serde(default)]
+    pub web_page: Option<Validators>,
+    /// Validators of the header JSON.
+    #[serde(default)]
+    pub header: Option<Validators>,
+    /// Validators of the chart data JSON.
+    #[serde(default)]
+    pub data: Option<Validators>,
+}
+
+impl TableValidators {
+    /// Validators of a stage, if any.
+    #[must_use]
+    pub const fn get(&self, stage: FetchStage) -> Option<&Validators> {
+        match stage {
+            FetchStage::WebPage => self.web_page.as_ref(),
+            FetchStage::Header => self.header.as_ref(),
+            FetchStage::Data => self.data.as_ref(),
+            FetchStage::TableList => None,
+        }
+    }
+}
+
+/// Result of a conditional table fetch.
+pub enum TableUpdate {
+    /// The header or data changed since the validators were recorded.
+    Modified(Box<FetchedTable>),
+    /// Neither the header nor the data changed; the previously fetched table is still current.
+    NotModified,
+}
+
+/// A header JSON URL candidate that was tried and rejected.
+#[derive(Debug)]
+pub struct HeaderAttempt {
+    /// Candidate found in the web page.
+    pub candidate: HeaderCandidate,
+    /// Candidate URL resolved against the web page URL, if it could be resolved.
+    pub url: Option<url::Url>,
+    /// Why the candidate was rejected.
+    pub error: Error,
 }
 
 /// Result of fetching a table list with its raw JSON string.
 pub struct FetchedTableList {
     /// Parsed list entries.
     pub tables: Vec<BmsTableInfo>,
-    /// Raw JSON string actually used for parsing.
+    /// Raw JSON string actually used for parsing (the scraped list as JSON when the page is an HTML table).
     pub raw_json: String,
+    /// Encoding the list was decoded with, e.g. `UTF-8`.
+    pub encoding: String,
+}
+
+/// Selects the entries of a table list to fetch, by symbol or tag.
+///
+/// An empty filter keeps every entry. Otherwise an entry is kept when its symbol is one of the selected
+/// symbols (if any are selected) and one of its tags is one of the selected tags (if any are selected).
+/// Tags are read from the `tag`, `tag1`, `tag2`, ... fields of the entry and compared case-insensitively.
+///
+/// # Example
+///
+/// ```rust
+/// use bms_table::{BmsTableInfo, fetch::ListFilter};
+///
+/// let entry: BmsTableInfo = serde_json::from_str(
+///     r#"{"name": "Satellite", "symbol": "sl", "url": "https://stellabms.xyz/sl/table.html", "tag1": "SP"}"#,
+/// )?;
+/// assert!(ListFilter::new().tag("sp").matches(&entry));
+/// assert!(!ListFilter::new().symbol("st").matches(&entry));
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListFilter {
+    /// Selected symbols; empty selects every symbol.
+    symbols: Vec<String>,
+    /// Selected tags; empty selects every entry, tagged or not.
+    tags: Vec<String>,
+}
+
+impl ListFilter {
+    /// Create a filter keeping every entry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keep entries with `symbol`, e.g. `sl`.
+    #[must_use]
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbols.push(symbol.into());
+        self
+    }
+
+    /// Also keep entries tagged `tag`, e.g. `SP`.
+    #[must_use]
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Whether `entry` is selected.
+    #[must_use]
+    pub fn matches(&self, entry: &BmsTableInfo) -> bool {
+        let symbol_matches = self.symbols.is_empty() || self.symbols.contains(&entry.symbol);
+        let tag_matches = self.tags.is_empty()
+            || entry_tags(entry).any(|tag| self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        symbol_matches && tag_matches
+    }
+}
+
+/// Tags of a table list entry: the string values of its `tag` and `tag<N>` fields.
+fn entry_tags(entry: &BmsTableInfo) -> impl Iterator<Item = &str> {
+    entry
+        .extra
+        .iter()
+        .filter(|(key, _)| {
+            key.strip_prefix("tag")
+                .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .filter_map(|(_, value)| value.as_str())
+        .map(str::trim)
+}
+
+/// A table list entry and the outcome of fetching its table.
+pub struct ListedTable {
+    /// Entry of the list.
+    pub info: BmsTableInfo,
+    /// The fetched table, or why it could not be fetched.
+    pub result: Result<FetchedTable>,
+}
+
+/// Result of fetching every selected table of a table list.
+pub struct FetchedTables {
+    /// Selected entries with their outcome, in list order.
+    pub tables: Vec<ListedTable>,
+    /// Entries left out by the [`ListFilter`], in list order.
+    pub skipped: Vec<BmsTableInfo>,
+}
+
+impl FetchedTables {
+    /// Count the entries by outcome.
+    #[must_use]
+    pub fn summary(&self) -> ListSummary {
+        let fetched = self.tables.iter().filter(|t| t.result.is_ok()).count();
+        ListSummary {
+            listed: self.tables.len() + self.skipped.len(),
+            skipped: self.skipped.len(),
+            fetched,
+            failed: self.tables.len() - fetched,
+        }
+    }
+
+    /// Iterate over the successfully fetched tables.
+    pub fn fetched(&self) -> impl Iterator<Item = (&BmsTableInfo, &FetchedTable)> {
+        self.tables
+            .iter()
+            .filter_map(|t| t.result.as_ref().ok().map(|fetched| (&t.info, fetched)))
+    }
+
+    /// Iterate over the entries whose table could not be fetched, with the reason.
+    pub fn failed(&self) -> impl Iterator<Item = (&BmsTableInfo, &Error)> {
+        self.tables
+            .iter()
+            .filter_map(|t| t.result.as_ref().err().map(|error| (&t.info, error)))
+    }
+}
+
+/// Entry counts of [`FetchedTables`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ListSummary {
+    /// Entries in the list.
+    pub listed: usize,
+    /// Entries left out by the filter.
+    pub skipped: usize,
+    /// Tables fetched successfully.
+    pub fetched: usize,
+    /// Tables that could not be fetched.
+    pub failed: usize,
 }
 
 /// Unified interface for fetching BMS tables.
@@ -56,7 +266,7 @@ pub trait TableFetcher {
     ///
     /// # Errors
     ///
-    /// Returns an error if fetching or parsing the table fails.
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the table fails.
     fn fetch_table(
         &self,
         web_url: url::Url,
@@ -66,7 +276,7 @@ pub trait TableFetcher {
     ///
     /// # Errors
     ///
-    /// Returns an error if fetching or parsing the list fails.
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the list fails.
     fn fetch_table_list(
         &self,
         web_url: url::Url,
@@ -75,7 +285,8 @@ pub trait TableFetcher {
 
 /// Return type of [`get_web_header_json_value`].
 ///
-/// - If the input is HTML, returns the URL extracted from `<meta name="bmstable">`;
+/// - If the input is HTML, returns the URL extracted from `<meta name="bmstable">`, or the header defined
+///   inline in a `<script>` block;
 /// - If the input is JSON, returns the parsed value of type `T`.
 pub enum HeaderQueryContent<T> {
     /// Extracted header JSON URL.
@@ -84,6 +295,23 @@ pub enum HeaderQueryContent<T> {
     Url(String),
     /// Parsed header JSON content.
     Value(T),
+    /// Header, and chart data if present, defined inline in a `<script>` block of the page.
+    Inline(InlineTable<T>),
+}
+
+/// Header and chart data defined as JavaScript literals in a `<script>` block.
+///
+/// Found by [`extract_inline_table`], e.g. from `var header = {...}` or `window.bmstable = {...}`.
+#[derive(Debug, Clone)]
+pub struct InlineTable<T> {
+    /// Parsed header.
+    pub header: T,
+    /// Header converted to JSON.
+    pub header_raw: String,
+    /// Chart data defined next to the header, if any.
+    pub data: Option<BmsTableData>,
+    /// Chart data converted to JSON; present together with `data`.
+    pub data_raw: Option<String>,
 }
 
 /// Remove non-printable control characters from JSON text.
@@ -95,51 +323,151 @@ pub fn replace_control_chars(s: &str) -> String {
     s.chars().filter(|ch: &char| !ch.is_control()).collect()
 }
 
+/// A repair applied to a document by [`parse_json_str_with_repairs`] to make it valid JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JsonRepair {
+    /// Illegal control characters were removed, see [`replace_control_chars`].
+    ControlChars,
+    /// A JSONP callback call around the value was removed, e.g. `callback([...])`.
+    Callback,
+    /// A variable assignment or default export of the value was removed, e.g. `var mdata = [...]` or
+    /// `export default [...]`.
+    Assignment,
+    /// A trailing semicolon was removed.
+    TrailingSemicolon,
+    /// Trailing commas before `]` or `}` were removed.
+    TrailingCommas,
+    /// Comments were removed.
+    Comments,
+    /// JavaScript literal syntax was converted, e.g. unquoted keys, single-quoted strings or `undefined`.
+    JsSyntax,
+}
+
+/// Result of [`parse_json_str_with_repairs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairedJson<T> {
+    /// Parsed value.
+    pub value: T,
+    /// JSON text the value was parsed from; equal to the input when no repair was needed.
+    pub json: String,
+    /// Repairs applied to the input, in the order listed by [`JsonRepair`]; empty when the input was valid.
+    pub repairs: Vec<JsonRepair>,
+}
+
+/// Parse JSON from a raw string, repairing common defects of table files.
+///
+/// Tries, in order, until one deserializes as `T`:
+/// 1. the original `raw`;
+/// 2. `raw` without illegal control characters ([`replace_control_chars`]);
+/// 3. `raw` read as a JavaScript file holding a single literal, such as `callback([...]);`, `var mdata = [...];`
+///    or `export default [...]`, with comments, trailing commas and other JavaScript literal syntax converted
+///    to JSON; control characters are removed from the result if it still does not parse.
+///
+/// # Errors
+///
+/// Returns [`Error::Json`] with the JSON error of the control-character-cleaned string when no attempt
+/// deserializes.
+pub fn parse_json_str_with_repairs<T: DeserializeOwned>(raw: &str) -> Result<RepairedJson<T>> {
+    repair_json(raw).map_err(|source| Error::Json { url: None, source })
+}
+
 /// Parse JSON from a raw string with a cleaning fallback.
 ///
-/// Tries to deserialize from the original `raw` first. If it fails, removes illegal
-/// control characters using [`replace_control_chars`] and retries. Returns the parsed
-/// value and the raw string that was successfully used.
+/// Same as [`parse_json_str_with_repairs`], returning the parsed value and the JSON text that was
+/// successfully used.
 ///
 /// # Errors
 ///
-/// Returns an error when both the original and cleaned strings fail to deserialize.
+/// Returns [`Error::Json`] with the JSON error of the control-character-cleaned string when no attempt
+/// deserializes.
 pub fn parse_json_str_with_fallback<T: DeserializeOwned>(raw: &str) -> Result<(T, String)> {
-    match serde_json::from_str::<T>(raw) {
-        Ok(v) => Ok((v, raw.to_string())),
-        Err(_) => {
-            let cleaned = replace_control_chars(raw);
-            let v = serde_json::from_str::<T>(&cleaned)?;
-            Ok((v, cleaned))
-        }
+    parse_json_str_with_repairs(raw).map(|repaired| (repaired.value, repaired.json))
+}
+
+/// Like [`parse_json_str_with_fallback`], keeping the JSON error for the caller to classify by document.
+pub(crate) fn json_with_fallback<T: DeserializeOwned>(
+    raw: &str,
+) -> serde_json::Result<(T, String)> {
+    repair_json(raw).map(|repaired| (repaired.value, repaired.json))
+}
+
+/// Implementation of [`parse_json_str_with_repairs`].
+fn repair_json<T: DeserializeOwned>(raw: &str) -> serde_json::Result<RepairedJson<T>> {
+    let parsed = |json: String, repairs: Vec<JsonRepair>| {
+        serde_json::from_str::<T>(&json).map(|value| RepairedJson {
+            value,
+            json,
+            repairs,
+        })
+    };
+    if let Ok(repaired) = parsed(raw.to_string(), Vec::new()) {
+        return Ok(repaired);
     }
+    let error = match parsed(replace_control_chars(raw), vec![JsonRepair::ControlChars]) {
+        Ok(repaired) => return Ok(repaired),
+        Err(error) => error,
+    };
+    let Some(wrapped) = js::unwrap(raw) else {
+        return Err(error);
+    };
+    let conversion = js::to_json(wrapped.literal);
+    let mut repairs: Vec<JsonRepair> = [
+        (wrapped.callback, JsonRepair::Callback),
+        (wrapped.assignment, JsonRepair::Assignment),
+        (wrapped.semicolon, JsonRepair::TrailingSemicolon),
+        (conversion.trailing_commas, JsonRepair::TrailingCommas),
+        (
+            conversion.comments || wrapped.comments,
+            JsonRepair::Comments,
+        ),
+        (conversion.js_syntax, JsonRepair::JsSyntax),
+    ]
+    .into_iter()
+    .filter_map(|(applied, repair)| applied.then_some(repair))
+    .collect();
+    if let Ok(repaired) = parsed(conversion.json.clone(), repairs.clone()) {
+        return Ok(repaired);
+    }
+    repairs.insert(0, JsonRepair::ControlChars);
+    parsed(replace_control_chars(&conversion.json), repairs).map_err(|_| error)
 }
 
 /// Parse a response string into the header JSON or its URL.
 ///
 /// Strategy: first attempt to parse as JSON; if it fails, parse as HTML and extract the bmstable URL.
+/// A URL declared by `<meta name="bmstable">` or `<link rel="bmstable">` is preferred over a header defined
+/// inline in a `<script>` block, which is in turn preferred over URLs found by the weaker heuristics of
+/// [`HeaderStrategy`].
 ///
 /// # Returns
 ///
 /// - `HeaderQueryContent::Value`: input is JSON;
-/// - `HeaderQueryContent::Url`: input is HTML.
+/// - `HeaderQueryContent::Url`: input is HTML linking a header;
+/// - `HeaderQueryContent::Inline`: input is HTML defining the header in a script.
 ///
 /// # Errors
 ///
-/// Returns an error when the input is HTML but the bmstable field cannot be found.
+/// Returns [`Error::HeaderNotFound`] when the input is HTML but the bmstable field cannot be found.
 pub fn get_web_header_json_value<T: DeserializeOwned>(
     response_str: &str,
 ) -> Result<HeaderQueryContent<T>> {
     // First try parsing as JSON (remove illegal control characters before parsing); if it fails, treat as HTML and extract the bmstable URL
     let cleaned = replace_control_chars(response_str);
-    match serde_json::from_str::<T>(&cleaned) {
-        Ok(header_json) => Ok(HeaderQueryContent::Value(header_json)),
-        Err(_) => {
-            let bmstable_url = try_extract_bmstable_from_html(response_str)
-                .context("When extracting bmstable url")?;
-            Ok(HeaderQueryContent::Url(bmstable_url))
-        }
+    if let Ok(header_json) = serde_json::from_str::<T>(&cleaned) {
+        return Ok(HeaderQueryContent::Value(header_json));
     }
+    let first = extract_header_candidates(response_str).into_iter().next();
+    if let Some(candidate) = &first
+        && candidate.strategy <= HeaderStrategy::LinkRel
+    {
+        return Ok(HeaderQueryContent::Url(candidate.url.clone()));
+    }
+    if let Some(inline) = extract_inline_table(response_str) {
+        return Ok(HeaderQueryContent::Inline(inline));
+    }
+    first
+        .map(|c| HeaderQueryContent::Url(c.url))
+        .ok_or(Error::HeaderNotFound { url: None })
 }
 
 /// Extract the header query content from a response string with a fallback cleaning step.
@@ -150,7 +478,7 @@ pub fn get_web_header_json_value<T: DeserializeOwned>(
 ///
 /// # Errors
 ///
-/// Returns an error when both attempts fail to extract a header URL or parse JSON.
+/// Returns [`Error::HeaderNotFound`] when both attempts fail to extract a header URL or parse JSON.
 pub fn header_query_with_fallback<T: DeserializeOwned>(
     raw: &str,
 ) -> Result<(HeaderQueryContent<T>, String)> {
@@ -164,101 +492,240 @@ pub fn header_query_with_fallback<T: DeserializeOwned>(
     }
 }
 
-/// Extract the JSON file URL pointed to by the bmstable field from HTML page content.
-///
-/// Scans `<meta>` tags looking for elements with `name="bmstable"` and reads their `content` attribute.
-///
-/// # Errors
+/// Heuristic that found a header JSON URL candidate, in priority order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HeaderStrategy {
+    /// `content` of `<meta name="bmstable">` or `<meta property="bmstable">`.
+    MetaBmstable,
+    /// `href` of `<link rel="bmstable">`.
+    LinkRel,
+    /// `href` of an `<a>` pointing to a `*header*.json` file.
+    AnchorHref,
+    /// `href` of a `<link>` pointing to a `*header*.json` file.
+    LinkHref,
+    /// `src` of a `<script>` pointing to a `*header*.json` file.
+    ScriptSrc,
+    /// `content` of a `<meta>` pointing to a `*header*.json` file.
+    MetaContent,
+    /// A `*header*.json` string anywhere in the page text.
+    RawText,
+}
+
+/// Where in the page a header URL candidate was found.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CandidateOrigin {
+    /// An HTML element, given as its outer HTML.
+    Element(String),
+    /// A byte range of the raw page text.
+    Text(Range<usize>),
+}
+
+/// A header JSON URL candidate found in an HTML page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HeaderCandidate {
+    /// Candidate URL as written in the page; may be relative.
+    pub url: String,
+    /// Heuristic that found the candidate.
+    pub strategy: HeaderStrategy,
+    /// Element or text span the candidate came from.
+    pub origin: CandidateOrigin,
+}
+
+/// List every header JSON URL candidate of an HTML page.
 ///
-/// Returns an error when the target tag is not found or `content` is empty.
-pub fn try_extract_bmstable_from_html(html_content: &str) -> Result<String> {
+/// Candidates are ordered by [`HeaderStrategy`] priority, then by position in the page; the first one is
+/// what [`try_extract_bmstable_from_html`] returns. The same URL may be found by several strategies.
+#[must_use]
+pub fn extract_header_candidates(html_content: &str) -> Vec<HeaderCandidate> {
     let document = Html::parse_document(html_content);
-    let meta_selector = Selector::parse("meta").map_err(|_| anyhow!("meta tag not found"))?;
-    let link_selector = Selector::parse("link").ok();
-    let a_selector = Selector::parse("a").ok();
-    let script_selector = Selector::parse("script").ok();
-
-    let find_attr = |selector: &Selector,
-                     attr: &str,
-                     keep: &mut dyn FnMut(&ElementRef<'_>, &str) -> bool|
-     -> Option<String> {
-        for element in document.select(selector) {
+    let mut candidates = Vec::new();
+
+    let mut collect = |tag: &str,
+                       attr: &str,
+                       strategy: HeaderStrategy,
+                       keep: &dyn Fn(&ElementRef<'_>, &str) -> bool| {
+        let Ok(selector) = Selector::parse(tag) else {
+            return;
+        };
+        for element in document.select(&selector) {
             if let Some(value) = element.value().attr(attr)
                 && keep(&element, value)
             {
-                return Some(value.to_string());
+                candidates.push(HeaderCandidate {
+                    url: value.to_string(),
+                    strategy,
+                    origin: CandidateOrigin::Element(element.html()),
+                });
             }
         }
-        None
     };
 
-    let candidate = meta_bmstable(&document, &meta_selector)
-        .or_else(|| {
-            let mut keep = |element: &ElementRef<'_>, href: &str| {
+    collect(
+        "meta",
+        "content",
+        HeaderStrategy::MetaBmstable,
+        &|element, content| {
+            let is_bmstable = |name| {
                 element
                     .value()
-                    .attr("rel")
+                    .attr(name)
                     .is_some_and(|v| v.eq_ignore_ascii_case("bmstable"))
-                    && !href.is_empty()
             };
-            link_selector
-                .as_ref()
-                .and_then(|sel| find_attr(sel, "href", &mut keep))
-        })
-        .or_else(|| {
-            let mut keep = |_: &ElementRef<'_>, href: &str| contains_header_json(href);
-            a_selector
-                .as_ref()
-                .and_then(|sel| find_attr(sel, "href", &mut keep))
-        })
-        .or_else(|| {
-            let mut keep = |_: &ElementRef<'_>, href: &str| contains_header_json(href);
-            link_selector
-                .as_ref()
-                .and_then(|sel| find_attr(sel, "href", &mut keep))
-        })
-        .or_else(|| {
-            let mut keep = |_: &ElementRef<'_>, src: &str| contains_header_json(src);
-            script_selector
-                .as_ref()
-                .and_then(|sel| find_attr(sel, "src", &mut keep))
-        })
-        .or_else(|| {
-            let mut keep = |_: &ElementRef<'_>, content: &str| contains_header_json(content);
-            find_attr(&meta_selector, "content", &mut keep)
+            (is_bmstable("name") || is_bmstable("property")) && !content.is_empty()
+        },
+    );
+    collect("link", "href", HeaderStrategy::LinkRel, &|element, href| {
+        element
+            .value()
+            .attr("rel")
+            .is_some_and(|v| v.eq_ignore_ascii_case("bmstable"))
+            && !href.is_empty()
+    });
+    collect("a", "href", HeaderStrategy::AnchorHref, &|_, href| {
+        contains_header_json(href)
+    });
+    collect("link", "href", HeaderStrategy::LinkHref, &|_, href| {
+        contains_header_json(href)
+    });
+    collect("script", "src", HeaderStrategy::ScriptSrc, &|_, src| {
+        contains_header_json(src)
+    });
+    collect(
+        "meta",
+        "content",
+        HeaderStrategy::MetaContent,
+        &|_, content| contains_header_json(content),
+    );
+
+    candidates.extend(find_header_json_in_text(html_content).filter_map(|span| {
+        Some(HeaderCandidate {
+            url: html_content.get(span.clone())?.to_string(),
+            strategy: HeaderStrategy::RawText,
+            origin: CandidateOrigin::Text(span),
         })
-        .or_else(|| {
-            find_header_json_in_text(html_content)
-                .map(|(start, end)| html_content[start..end].to_string())
-        });
+    }));
+    candidates
+}
 
-    candidate.map_or_else(
-        || Err(anyhow!("bmstable field or header JSON hint not found")),
-        Ok,
-    )
+/// Extract the JSON file URL pointed to by the bmstable field from HTML page content.
+///
+/// Returns the first candidate of [`extract_header_candidates`]: `<meta name="bmstable">` is preferred,
+/// followed by the other heuristics described by [`HeaderStrategy`].
+///
+/// # Errors
+///
+/// Returns [`Error::HeaderNotFound`] when no candidate is found.
+pub fn try_extract_bmstable_from_html(html_content: &str) -> Result<String> {
+    extract_header_candidates(html_content)
+        .into_iter()
+        .next()
+        .map(|c| c.url)
+        .ok_or(Error::HeaderNotFound { url: None })
+}
+
+/// Find a header, and chart data next to it, defined as JavaScript literals in the `<script>` blocks of a page.
+///
+/// Every object or array literal assigned to a name (`var header = {...}`, `window.bmstable = {...}`) is
+/// considered. The header is the first object that is a valid [`BmsTableHeader`], or the `header` field of
+/// such an object; chart data is the first non-empty array of charts having a title or hash, or the `data`
+/// field of an object. When chart data is found, a header without `data_url` is accepted too, with an empty
+/// `data_url`. Returns `None` when no header is found.
+#[must_use]
+pub fn extract_inline_table<T: DeserializeOwned>(html_content: &str) -> Option<InlineTable<T>> {
+    let document = Html::parse_document(html_content);
+    let selector = Selector::parse("script:not([src])").ok()?;
+    let mut header: Option<(T, String)> = None;
+    // Only used when the chart data is inline as well.
+    let mut dataless_header: Option<(T, String)> = None;
+    let mut data: Option<(BmsTableData, String)> = None;
+    for script in document.select(&selector) {
+        let script: String = script.text().collect();
+        for literal in js::assigned_literals(&script) {
+            let Ok(value) = serde_json::from_str::<Value>(&js::to_json(literal).json) else {
+                continue;
+            };
+            let nested = |key| value.get(key).into_iter();
+            if header.is_none() {
+                header = std::iter::once(&value)
+                    .chain(nested("header"))
+                    .find_map(inline_header);
+            }
+            if header.is_none() && dataless_header.is_none() {
+                dataless_header = std::iter::once(&value)
+                    .chain(nested("header"))
+                    .find_map(inline_header_without_data_url);
+            }
+            if data.is_none() {
+                data = std::iter::once(&value)
+                    .chain(nested("data"))
+                    .find_map(inline_data);
+            }
+        }
+    }
+    let (header, header_raw) = header.or_else(|| dataless_header.filter(|_| data.is_some()))?;
+    let (data, data_raw) = data.unzip();
+    Some(InlineTable {
+        header,
+        header_raw,
+        data,
+        data_raw,
+    })
 }
 
-/// Find the start and end indices of a substring like "*header*.json" in raw text.
-fn find_header_json_in_text(s: &str) -> Option<(usize, usize)> {
+/// Parse an inline header candidate, returning it with its JSON text.
+fn inline_header<T: DeserializeOwned>(value: &Value) -> Option<(T, String)> {
+    BmsTableHeader::deserialize(value).ok()?;
+    Some((T::deserialize(value).ok()?, value.to_string()))
+}
+
+/// Parse an inline header candidate lacking `data_url` as if it were empty, returning it with its JSON text.
+fn inline_header_without_data_url<T: DeserializeOwned>(value: &Value) -> Option<(T, String)> {
+    let mut object = value.as_object()?.clone();
+    if object.contains_key("data_url") {
+        return None;
+    }
+    object.insert("data_url".to_string(), Value::String(String::new()));
+    inline_header(&Value::Object(object))
+}
+
+/// Parse an inline chart data candidate, returning it with its JSON text.
+fn inline_data(value: &Value) -> Option<(BmsTableData, String)> {
+    if value.as_array().is_none_or(Vec::is_empty) {
+        return None;
+    }
+    let data = BmsTableData::deserialize(value).ok()?;
+    data.charts
+        .iter()
+        .all(|c| c.title.is_some() || c.md5.is_some() || c.sha256.is_some())
+        .then(|| (data, value.to_string()))
+}
+
+/// Find the byte ranges of substrings like "*header*.json" in raw text, in order.
+fn find_header_json_in_text(s: &str) -> impl Iterator<Item = Range<usize>> + '_ {
     let lower = s.to_ascii_lowercase();
     let mut pos = 0;
-    while let Some(idx) = lower[pos..].find("header") {
-        let global_idx = pos + idx;
-        // Look for .json after header
-        if let Some(json_rel) = lower[global_idx..].find(".json") {
+    let mut last: Option<Range<usize>> = None;
+    std::iter::from_fn(move || {
+        while let Some(idx) = lower.get(pos..)?.find("header") {
+            let global_idx = pos + idx;
+            pos = global_idx + 6; // skip "header"
+            // Look for .json after header
+            let json_rel = lower.get(global_idx..)?.find(".json")?;
             let end = global_idx + json_rel + ".json".len();
             // Try to find the nearest quote or whitespace before as the start
-            let start = lower[..global_idx]
+            let start = lower
+                .get(..global_idx)?
                 .rfind(|c: char| c == '"' || c == '\'' || c.is_whitespace())
-                .map(|i| i + 1)
-                .unwrap_or(global_idx);
-            if end > start {
-                return Some((start, end));
+                .map_or(global_idx, |i| i + 1);
+            let span = start..end;
+            // Several "header"s before the same ".json" yield the same span
+            if end > start && last.as_ref() != Some(&span) {
+                last = Some(span.clone());
+                return Some(span);
             }
         }
-        pos = global_idx + 6; // skip "header"
-    }
-    None
+        None
+    })
 }
 
 /// Check whether the string contains "header" and ends with ".json".
@@ -266,24 +733,3 @@ fn contains_header_json(s: &str) -> bool {
     let ls = s.to_ascii_lowercase();
     ls.contains("header") && ls.ends_with(".json")
 }
-
-/// Extract bmstable content from `<meta>` tags.
-fn meta_bmstable(document: &Html, meta_selector: &Selector) -> Option<String> {
-    for element in document.select(meta_selector) {
-        let is_bmstable = element
-            .value()
-            .attr("name")
-            .is_some_and(|v| v.eq_ignore_ascii_case("bmstable"))
-            || element
-                .value()
-                .attr("property")
-                .is_some_and(|v| v.eq_ignore_ascii_case("bmstable"));
-        if is_bmstable
-            && let Some(content_attr) = element.value().attr("content")
-            && !content_attr.is_empty()
-        {
-            return Some(content_attr.to_string());
-        }
-    }
-    None
-}
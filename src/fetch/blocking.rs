@@ -0,0 +1,244 @@
+//! Synchronous network fetching based on `reqwest::blocking`
+//!
+//! Provides the same page → header → chart data flow as [`crate::fetch::reqwest::Fetcher`], driven by the same
+//! [`TableResolver`], for synchronous programs: no async runtime has to be set up by the caller.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use bms_table::fetch::blocking::Fetcher;
+//! let fetcher = Fetcher::lenient()?;
+//! let table = fetcher.fetch_table("https://stellabms.xyz/sl/table.html")?.table;
+//! assert!(!table.data.charts.is_empty());
+//! # Ok(())
+//! # }
+//! ```
+#![cfg(feature = "blocking")]
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use reqwest::{IntoUrl, blocking::Client};
+
+use crate::{
+    error::{Error, FetchStage, Result},
+    fetch::{
+        CachePolicy, DiskCache, FetchedTable, FetchedTableList, HttpRequest, HttpResponse,
+        ResolveStep, RetryPolicy, TableResolver, TableUpdate, TableValidators,
+        cache::CachePlan,
+        reqwest::{
+            FetcherBuilder, conditional_headers, http_response, network_error,
+            table_list_from_text, unexpected_not_modified,
+        },
+        throttle::{HostLimiter, HostLimits},
+    },
+};
+
+/// Synchronous fetcher wrapper around a reusable [`reqwest::blocking::Client`].
+///
+/// Must not be used from within an async runtime; use [`crate::fetch::reqwest::Fetcher`] there instead.
+#[derive(Clone)]
+pub struct Fetcher {
+    /// Underlying HTTP client.
+    client: Client,
+    /// Request timeouts overriding the client timeout for some stages.
+    stage_timeouts: BTreeMap<FetchStage, Duration>,
+    /// On-disk cache and how it is used, if any.
+    cache: Option<(DiskCache, CachePolicy)>,
+    /// When failed requests are sent again.
+    retry: RetryPolicy,
+    /// Per-host limits, shared by all clones.
+    hosts: Arc<HostLimiter>,
+}
+
+impl Fetcher {
+    /// Create a fetcher from an existing [`reqwest::blocking::Client`], making a single attempt per request and
+    /// with no per-host limits.
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self::from_parts(
+            client,
+            BTreeMap::new(),
+            None,
+            RetryPolicy::none(),
+            HostLimiter::new(HostLimits::UNLIMITED),
+        )
+    }
+
+    /// Create a fetcher from a client and the other settings of a [`FetcherBuilder`].
+    pub(crate) fn from_parts(
+        client: Client,
+        stage_timeouts: BTreeMap<FetchStage, Duration>,
+        cache: Option<(DiskCache, CachePolicy)>,
+        retry: RetryPolicy,
+        hosts: HostLimiter,
+    ) -> Self {
+        Self {
+            client,
+            stage_timeouts,
+            cache,
+            retry,
+            hosts: Arc::new(hosts),
+        }
+    }
+
+    /// Start configuring a fetcher from secure defaults; finish with [`FetcherBuilder::build_blocking`].
+    #[must_use]
+    pub fn builder() -> FetcherBuilder {
+        FetcherBuilder::new()
+    }
+
+    /// Create a fetcher with a more compatible, browser-like HTTP client configuration.
+    ///
+    /// Equivalent to `FetcherBuilder::lenient().build_blocking()`; note that it accepts invalid TLS certificates.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Client`] if building the underlying HTTP client fails.
+    pub fn lenient() -> Result<Self> {
+        FetcherBuilder::lenient().build_blocking()
+    }
+
+    /// Borrow the underlying [`reqwest::blocking::Client`].
+    #[must_use]
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Fetch and parse a complete BMS difficulty table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the table fails.
+    pub fn fetch_table(&self, web_url: impl IntoUrl) -> Result<FetchedTable> {
+        let web_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
+
+        match self.resolve(TableResolver::new(web_url.clone()))? {
+            TableUpdate::Modified(fetched) => Ok(*fetched),
+            TableUpdate::NotModified => Err(unexpected_not_modified(web_url)),
+        }
+    }
+
+    /// Fetch a table again, unless it did not change since `validators` were recorded.
+    ///
+    /// See [`crate::fetch::reqwest::Fetcher::fetch_table_if_modified`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the table fails.
+    pub fn fetch_table_if_modified(
+        &self,
+        web_url: impl IntoUrl,
+        validators: &TableValidators,
+    ) -> Result<TableUpdate> {
+        let web_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
+
+        self.resolve(TableResolver::with_validators(web_url, validators.clone()))
+    }
+
+    /// Fetch a list of BMS difficulty tables.
+    ///
+    /// Like [`crate::fetch::reqwest::Fetcher::fetch_table_list`], an HTML table is scraped when the body is
+    /// not JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the list fails.
+    pub fn fetch_table_list(&self, web_url: impl IntoUrl) -> Result<FetchedTableList> {
+        let list_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
+
+        let request = HttpRequest::new(list_url.clone(), FetchStage::TableList);
+        let text = self.fetch(&request, &list_url)?.into_text(&request)?;
+        table_list_from_text(list_url, &text)
+    }
+
+    /// Drive a [`TableResolver`] until it finishes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Error`] that ended the resolution.
+    fn resolve(&self, mut resolver: TableResolver) -> Result<TableUpdate> {
+        let table_url = resolver.request().url.clone();
+        loop {
+            let step = match self.fetch(resolver.request(), &table_url) {
+                Ok(response) => resolver.respond(response)?,
+                Err(error) => resolver.fail(error)?,
+            };
+            match step {
+                ResolveStep::Fetch(next) => resolver = next,
+                ResolveStep::Done(fetched) => return Ok(TableUpdate::Modified(fetched)),
+                ResolveStep::NotModified => return Ok(TableUpdate::NotModified),
+            }
+        }
+    }
+
+    /// Answer an [`HttpRequest`] of the table fetched from `table_url`, through the cache if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotCached`] when offline, and the errors of [`Fetcher::send`].
+    fn fetch(&self, request: &HttpRequest, table_url: &reqwest::Url) -> Result<HttpResponse> {
+        let Some((cache, policy)) = &self.cache else {
+            return self.send(request);
+        };
+        match cache.plan(request, policy, policy.ttl_for(table_url))? {
+            CachePlan::Serve(response) => Ok(response),
+            CachePlan::Fetch {
+                request: sent,
+                cached,
+            } => {
+                let result = self.send(&sent);
+                cache.complete(request, cached, result, policy)
+            }
+        }
+    }
+
+    /// Perform an [`HttpRequest`], retrying it according to the [`RetryPolicy`], and collect the last response,
+    /// whatever its status.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Network`] if the last attempt fails or its body cannot be read.
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let mut attempt = 1;
+        loop {
+            let outcome = self.send_once(request);
+            let Some(delay) = self.retry.retry_delay(attempt, &outcome) else {
+                return outcome;
+            };
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Perform one attempt of an [`HttpRequest`] once the per-host limits allow it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Network`] if the request fails or the body cannot be read.
+    fn send_once(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let HttpRequest { url, stage, .. } = request;
+        let _permit = self.hosts.acquire_blocking(url);
+        let mut builder = self
+            .client
+            .get(url.clone())
+            .headers(conditional_headers(request));
+        if let Some(timeout) = self.stage_timeouts.get(stage) {
+            builder = builder.timeout(*timeout);
+        }
+        let response = builder.send().map_err(|e| network_error(*stage, url, e))?;
+        let status = response.status();
+        let final_url = response.url().clone();
+        let headers = response.headers().clone();
+        let bytes = response
+            .bytes()
+            .map_err(|e| network_error(*stage, url, e))?;
+        Ok(http_response(final_url, status, &headers, bytes.to_vec()))
+    }
+}
@@ -0,0 +1,347 @@
+//! Persistent on-disk cache of fetched documents
+//!
+//! [`DiskCache`] stores the body of every fetched web page, header and data document on disk, keyed by the
+//! requested URL, together with its fetch time and HTTP validators. [`CachePolicy`] decides how a fetcher
+//! uses it:
+//!
+//! - a copy younger than the TTL of its table is served without touching the network;
+//! - an older copy is revalidated with `If-None-Match`/`If-Modified-Since`, and served again on `304`;
+//! - [`CacheMode::Offline`] only serves cached copies, whatever their age, and never touches the network;
+//! - [`CacheMode::StaleOnError`] serves the last good copy when the site cannot be reached or answers with
+//!   a server error.
+//!
+//! Each entry is two files named after a hash of the URL: `<hash>.json` with the metadata and `<hash>.body`
+//! with the raw body. Writing to the cache is best effort: a failed write never fails a fetch.
+#![cfg(feature = "reqwest")]
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error::{Error, Result},
+    fetch::{HttpRequest, HttpResponse},
+};
+
+/// Default time a cached copy is served without revalidation.
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// HTTP status answered to a conditional request whose document did not change.
+const NOT_MODIFIED: u16 = 304;
+
+/// Directory of cached documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCache {
+    /// Directory holding the entry files.
+    dir: PathBuf,
+}
+
+/// A cached document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Requested URL, the key of the entry.
+    pub url: Url,
+    /// Final URL after redirects.
+    pub final_url: Url,
+    /// When the document was fetched or last revalidated.
+    pub fetched_at: SystemTime,
+    /// Value of the `Content-Type` header, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Value of the `ETag` header, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Raw body, stored next to the metadata.
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+/// How a fetcher uses its [`DiskCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CacheMode {
+    /// Serve fresh copies, revalidate stale ones, and fail when the network fails.
+    #[default]
+    Normal,
+    /// Only serve cached copies, whatever their age; uncached documents fail with [`Error::NotCached`].
+    Offline,
+    /// Like [`CacheMode::Normal`], but serve the stale copy when the network fails or the server answers with
+    /// a transient error status (408, 429 or 5xx).
+    StaleOnError,
+}
+
+/// Cache mode and freshness lifetimes of a fetcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// How the cache is used.
+    mode: CacheMode,
+    /// Freshness lifetime of tables without their own.
+    ttl: Duration,
+    /// Freshness lifetimes of some tables, by web page URL.
+    table_ttls: BTreeMap<Url, Duration>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            mode: CacheMode::default(),
+            ttl: DEFAULT_TTL,
+            table_ttls: BTreeMap::new(),
+        }
+    }
+}
+
+impl CachePolicy {
+    /// Create a [`CacheMode::Normal`] policy with a one hour TTL.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how the cache is used.
+    #[must_use]
+    pub const fn mode(mut self, mode: CacheMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the freshness lifetime of tables without their own; [`Duration::ZERO`] always revalidates.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the freshness lifetime of the documents of one table, by the web page URL it is fetched from.
+    #[must_use]
+    pub fn table_ttl(mut self, web_url: Url, ttl: Duration) -> Self {
+        self.table_ttls.insert(web_url, ttl);
+        self
+    }
+
+    /// The configured cache mode.
+    #[must_use]
+    pub const fn cache_mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    /// Freshness lifetime of the documents of the table fetched from `web_url`.
+    #[must_use]
+    pub fn ttl_for(&self, web_url: &Url) -> Duration {
+        self.table_ttls.get(web_url).copied().unwrap_or(self.ttl)
+    }
+}
+
+/// What a fetcher does with a request, decided by [`DiskCache::plan`].
+pub(crate) enum CachePlan {
+    /// Answer with a cached copy.
+    Serve(HttpResponse),
+    /// Send `request`, which carries the validators of `cached` when the caller sent none, then hand the
+    /// result to [`DiskCache::complete`].
+    Fetch {
+        /// Request to send.
+        request: HttpRequest,
+        /// Stale cached copy, if any.
+        cached: Option<Box<CacheEntry>>,
+    },
+}
+
+impl DiskCache {
+    /// Use `dir` as cache directory; it is created on the first write.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Read the entry of `url`, if cached.
+    #[must_use]
+    pub fn get(&self, url: &Url) -> Option<CacheEntry> {
+        let (meta_path, body_path) = self.paths(url);
+        let meta = fs::read(meta_path).ok()?;
+        let mut entry: CacheEntry = serde_json::from_slice(&meta).ok()?;
+        // Hash collisions are told apart by the stored URL.
+        if entry.url != *url {
+            return None;
+        }
+        entry.body = fs::read(body_path).ok()?;
+        Some(entry)
+    }
+
+    /// Store an entry, replacing the previous entry of its URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the directory or the entry files cannot be written.
+    pub fn put(&self, entry: &CacheEntry) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let (meta_path, body_path) = self.paths(&entry.url);
+        let meta = serde_json::to_vec_pretty(entry).map_err(io::Error::other)?;
+        // Write the body first: metadata is only visible once its body is complete.
+        write_atomic(&body_path, &entry.body)?;
+        write_atomic(&meta_path, &meta)
+    }
+
+    /// Decide whether `request` is answered from the cache or sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotCached`] in [`CacheMode::Offline`] when the document is not cached.
+    pub(crate) fn plan(
+        &self,
+        request: &HttpRequest,
+        policy: &CachePolicy,
+        ttl: Duration,
+    ) -> Result<CachePlan> {
+        match (policy.mode, self.get(&request.url)) {
+            (CacheMode::Offline, None) => Err(Error::NotCached {
+                stage: request.stage,
+                url: request.url.clone(),
+            }),
+            (CacheMode::Offline, Some(entry)) => Ok(CachePlan::Serve(entry.respond(request))),
+            (_, Some(entry)) if entry.is_fresh(ttl) => Ok(CachePlan::Serve(entry.respond(request))),
+            (_, cached) => {
+                let mut sent = request.clone();
+                if let Some(entry) = cached.as_ref().filter(|_| !request.is_conditional()) {
+                    sent.if_none_match.clone_from(&entry.etag);
+                    sent.if_modified_since.clone_from(&entry.last_modified);
+                }
+                Ok(CachePlan::Fetch {
+                    request: sent,
+                    cached: cached.map(Box::new),
+                })
+            }
+        }
+    }
+
+    /// Store the result of a request sent for [`CachePlan::Fetch`], and answer `request` from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `result`, unless a stale copy is served in its place.
+    pub(crate) fn complete(
+        &self,
+        request: &HttpRequest,
+        cached: Option<Box<CacheEntry>>,
+        result: Result<HttpResponse>,
+        policy: &CachePolicy,
+    ) -> Result<HttpResponse> {
+        let stale_on_error = policy.mode == CacheMode::StaleOnError;
+        let response = match (result, cached) {
+            (Err(_), Some(entry)) if stale_on_error => return Ok(entry.respond(request)),
+            (Err(error), _) => return Err(error),
+            (Ok(response), cached) => match (response.status, cached) {
+                (200..=299, _) => {
+                    // Best effort: the response is served even if it cannot be cached.
+                    let _ = self.put(&CacheEntry::from_response(request, &response));
+                    response
+                }
+                // Not modified since the cached copy, whose validators were sent in place of the caller's.
+                (NOT_MODIFIED, Some(mut entry)) if !request.is_conditional() => {
+                    entry.fetched_at = SystemTime::now();
+                    entry.etag = response.etag.or(entry.etag);
+                    entry.last_modified = response.last_modified.or(entry.last_modified);
+                    let _ = self.put(&entry);
+                    entry.respond(request)
+                }
+                (408 | 429 | 500..=599, Some(entry)) if stale_on_error => entry.respond(request),
+                _ => response,
+            },
+        };
+        Ok(response)
+    }
+
+    /// Paths of the metadata and body files of `url`.
+    fn paths(&self, url: &Url) -> (PathBuf, PathBuf) {
+        let key = format!("{:016x}", fnv1a(url.as_str().as_bytes()));
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.body")),
+        )
+    }
+}
+
+impl CacheEntry {
+    /// Entry of a successful response to `request`, fetched now.
+    fn from_response(request: &HttpRequest, response: &HttpResponse) -> Self {
+        Self {
+            url: request.url.clone(),
+            final_url: response.url.clone(),
+            fetched_at: SystemTime::now(),
+            content_type: response.content_type.clone(),
+            etag: response.etag.clone(),
+            last_modified: response.last_modified.clone(),
+            body: response.body.clone(),
+        }
+    }
+
+    /// Whether the entry is younger than `ttl`.
+    #[must_use]
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        self.fetched_at.elapsed().is_ok_and(|age| age < ttl)
+    }
+
+    /// Answer `request` with the entry: `304 Not Modified` when the request sends the entry's validators
+    /// back, and the cached body otherwise.
+    fn respond(self, request: &HttpRequest) -> HttpResponse {
+        let matches =
+            |sent: &Option<String>, stored: &Option<String>| sent.is_some() && sent == stored;
+        let unchanged = matches(&request.if_none_match, &self.etag)
+            || (request.if_none_match.is_none()
+                && matches(&request.if_modified_since, &self.last_modified));
+        let mut response = if unchanged {
+            HttpResponse::new(self.final_url, NOT_MODIFIED, Vec::new())
+        } else {
+            HttpResponse::new(self.final_url, 200, self.body)
+        };
+        response.content_type = self.content_type;
+        response.etag = self.etag;
+        response.last_modified = self.last_modified;
+        response
+    }
+}
+
+/// Counter making the temporary file names of [`write_atomic`] unique within the process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file through a temporary file, so that readers never see it half written.
+///
+/// The temporary file is named after the target file, the process and a counter, so concurrent writers
+/// (even of the same target) never share one.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = path.with_file_name(name);
+    let result = fs::write(&tmp, contents).and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+        // Best effort: the write already failed, a leftover temporary file is harmless.
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and releases, used to name entry files.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,148 @@
+//! Character encoding detection
+//!
+//! Older Japanese table sites serve `Shift_JIS` or EUC-JP pages and JSON, often declaring the charset only in a
+//! `<meta charset>` tag, or not at all. [`decode_text`] turns the raw bytes of such a response into text,
+//! determining the encoding in this order:
+//!
+//! 1. a byte order mark;
+//! 2. the `charset` parameter of the `Content-Type` header;
+//! 3. a `<meta charset>` or `<meta http-equiv="Content-Type">` tag near the start of an HTML document;
+//! 4. UTF-8 if the bytes are valid UTF-8, otherwise whichever of `Shift_JIS` and EUC-JP decodes them best.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::fetch::encoding::{CharsetSource, decode_text};
+//!
+//! // "難易度表" in Shift_JIS, declared by a meta tag only.
+//! let mut page = b"<meta charset=\"Shift_JIS\"><title>".to_vec();
+//! page.extend_from_slice(&[0x93, 0xEF, 0x88, 0xD5, 0x93, 0x78, 0x95, 0x5C]);
+//! page.extend_from_slice(b"</title>");
+//!
+//! let decoded = decode_text(&page, Some("text/html"));
+//! assert_eq!(decoded.encoding, "Shift_JIS");
+//! assert_eq!(decoded.source, CharsetSource::MetaCharset);
+//! assert!(decoded.text.contains("難易度表"));
+//! ```
+
+use encoding_rs::{EUC_JP, Encoding, REPLACEMENT, SHIFT_JIS, UTF_8};
+
+/// Number of leading bytes searched for a `<meta>` charset declaration.
+const META_PRESCAN_LEN: usize = 4096;
+
+/// How the encoding of a document was determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharsetSource {
+    /// A byte order mark at the start of the document.
+    Bom,
+    /// The `charset` parameter of the `Content-Type` header.
+    ContentType,
+    /// A `<meta>` charset declaration in the HTML document.
+    MetaCharset,
+    /// Guessed from the bytes.
+    Heuristic,
+}
+
+/// Text decoded by [`decode_text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    /// Decoded text, without byte order mark.
+    pub text: String,
+    /// Name of the encoding used, e.g. `"UTF-8"`, `"Shift_JIS"` or `"EUC-JP"`.
+    pub encoding: &'static str,
+    /// How the encoding was determined.
+    pub source: CharsetSource,
+}
+
+/// Decode the raw bytes of a response, detecting their character encoding.
+///
+/// `content_type` is the value of the `Content-Type` header, if any. See the [module docs](self) for the
+/// detection order. Malformed sequences are replaced with U+FFFD.
+#[must_use]
+pub fn decode_text(bytes: &[u8], content_type: Option<&str>) -> DecodedText {
+    let (encoding, source) = detect_encoding(bytes, content_type);
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    DecodedText {
+        text: text.into_owned(),
+        encoding: encoding.name(),
+        source,
+    }
+}
+
+/// Determine the encoding of `bytes` and how it was found.
+fn detect_encoding(bytes: &[u8], content_type: Option<&str>) -> (&'static Encoding, CharsetSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, CharsetSource::Bom);
+    }
+    if let Some(encoding) = content_type
+        .and_then(charset_param)
+        .and_then(encoding_for_label)
+    {
+        return (encoding, CharsetSource::ContentType);
+    }
+    if let Some(encoding) = meta_charset(bytes) {
+        return (encoding, CharsetSource::MetaCharset);
+    }
+    (guess_encoding(bytes), CharsetSource::Heuristic)
+}
+
+/// Look up an encoding label, ignoring labels that do not name a usable encoding.
+fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes()).filter(|&e| e != REPLACEMENT)
+}
+
+/// Value of the `charset` parameter of a `Content-Type` value.
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+/// Encoding declared by a `<meta>` tag near the start of an HTML document.
+///
+/// Handles both `<meta charset="...">` and `<meta http-equiv="Content-Type" content="...; charset=...">`.
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = bytes.get(..META_PRESCAN_LEN).unwrap_or(bytes);
+    // Declarations are ASCII, so a lossy ASCII view is enough to find them.
+    let head: String = head
+        .iter()
+        .map(|&b| char::from(b.to_ascii_lowercase()).min('\x7f'))
+        .collect();
+    head.match_indices("<meta").find_map(|(start, _)| {
+        let tag = head.get(start..)?;
+        let tag = tag.get(..tag.find('>')?)?;
+        let value = tag.get(tag.find("charset")? + "charset".len()..)?;
+        let value = value.trim_start().strip_prefix('=')?.trim_start();
+        let value = value.trim_start_matches(['"', '\'']);
+        let end = value
+            .find(|c: char| matches!(c, '"' | '\'' | ';' | '/') || c.is_ascii_whitespace())
+            .unwrap_or(value.len());
+        encoding_for_label(value.get(..end)?)
+    })
+}
+
+/// Guess the encoding of undeclared bytes: UTF-8 if valid, otherwise the better of `Shift_JIS` and EUC-JP.
+///
+/// The better candidate is the one producing fewer malformed sequences, then more kana and kanji.
+fn guess_encoding(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    [SHIFT_JIS, EUC_JP]
+        .into_iter()
+        .min_by_key(|encoding| {
+            let (text, _) = encoding.decode_without_bom_handling(bytes);
+            let malformed = text.chars().filter(|&c| c == '\u{FFFD}').count();
+            let japanese = text.chars().filter(|&c| is_japanese(c)).count();
+            (malformed, std::cmp::Reverse(japanese))
+        })
+        .unwrap_or(UTF_8)
+}
+
+/// Whether `c` is a kana, a CJK ideograph or a full-width form.
+const fn is_japanese(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF00}'..='\u{FFEF}')
+}
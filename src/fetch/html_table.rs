@@ -0,0 +1,440 @@
+//! Scraping tables published only as HTML
+//!
+//! Several LR2-era difficulty tables never published a header or data JSON: their charts only exist as rows of
+//! an HTML `<table>`, with the level, title and artist in separate columns and the chart md5 in a link to
+//! LR2IR (`search.cgi?mode=ranking&bmsmd5=...`). [`scrape_table`] reads such a page into a [`BmsTable`]
+//! with a synthetic header, using a [`ColumnMapping`] that tells which column holds which field.
+//!
+//! Lists of tables are commonly shared the same way, as an HTML table with name, symbol, tag, comment and link
+//! columns; [`scrape_table_list`] reads them into a [`BmsTableList`] using a [`ListColumnMapping`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::fetch::html_table::{ColumnMapping, scrape_table};
+//!
+//! let html = r#"
+//! <title>Legacy Table</title>
+//! <table>
+//!   <tr><th>Lv</th><th>Title</th><th>Artist</th><th>IR</th></tr>
+//!   <tr>
+//!     <td>★1</td><td>Song</td><td>Someone</td>
+//!     <td><a href="http://www.dream-pro.info/~lavalse/LR2IR/search.cgi?mode=ranking&bmsmd5=d41d8cd98f00b204e9800998ecf8427e">LR2IR</a></td>
+//!   </tr>
+//! </table>
+//! "#;
+//!
+//! let mapping = ColumnMapping::detect(html).unwrap_or_default();
+//! let table = scrape_table(html, &mapping).unwrap();
+//! assert_eq!(table.header.name, "Legacy Table");
+//! assert_eq!(table.header.symbol, "★");
+//! assert_eq!(table.data.charts[0].level, "1");
+//! assert!(table.data.charts[0].md5.is_some());
+//! ```
+
+use std::collections::BTreeMap;
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+use crate::{BmsTable, BmsTableData, BmsTableHeader, BmsTableInfo, BmsTableList, ChartItem, Md5};
+
+/// Which columns of an HTML table hold which chart fields.
+///
+/// Columns are counted from 0 over the cells of each row; `None` means the table has no such column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// CSS selector of the table; the first matching element is scraped.
+    pub table: String,
+    /// Column holding the level, possibly prefixed with the table symbol (e.g. "★12").
+    pub level: Option<usize>,
+    /// Column holding the title.
+    pub title: Option<usize>,
+    /// Column holding the artist.
+    pub artist: Option<usize>,
+    /// Column holding the LR2IR link with the chart md5; `None` searches every cell of the row.
+    pub lr2ir: Option<usize>,
+    /// Column whose first link is the chart download URL.
+    pub url: Option<usize>,
+    /// Column whose first link is the differential file download URL.
+    pub url_diff: Option<usize>,
+}
+
+impl Default for ColumnMapping {
+    /// The common layout: level, title and artist in the first three columns, LR2IR link anywhere in the row.
+    fn default() -> Self {
+        Self {
+            table: "table".to_string(),
+            level: Some(0),
+            title: Some(1),
+            artist: Some(2),
+            lr2ir: None,
+            url: None,
+            url_diff: None,
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// Guess the mapping from the header cells of the first `<table>` of a page.
+    ///
+    /// Header cells are matched by their text, in English or Japanese (e.g. "Lv", "Title"/"曲名",
+    /// "Artist"/"アーティスト", "LR2IR", "URL"/"本体", "差分"). Returns `None` when no level or title
+    /// column is found.
+    #[must_use]
+    pub fn detect(html_content: &str) -> Option<Self> {
+        let document = Html::parse_document(html_content);
+        let table = document.select(&Selector::parse("table").ok()?).next()?;
+        let header = table_rows(table).find(|cells| {
+            cells
+                .iter()
+                .any(|cell| cell.value().name().eq_ignore_ascii_case("th"))
+        })?;
+        let mut mapping = Self {
+            level: None,
+            title: None,
+            artist: None,
+            ..Self::default()
+        };
+        for (index, cell) in header.iter().enumerate() {
+            let text = cell_text(*cell).to_lowercase();
+            let slot = if text.contains("差分") || text.contains("diff") {
+                &mut mapping.url_diff
+            } else if text.contains("lr2ir") || text == "ir" {
+                &mut mapping.lr2ir
+            } else if ["lv", "level", "レベル", "難易度"]
+                .iter()
+                .any(|k| text.contains(k))
+            {
+                &mut mapping.level
+            } else if ["title", "タイトル", "曲名"]
+                .iter()
+                .any(|k| text.contains(k))
+            {
+                &mut mapping.title
+            } else if ["artist", "アーティスト", "作者"]
+                .iter()
+                .any(|k| text.contains(k))
+            {
+                &mut mapping.artist
+            } else if ["url", "download", "dl", "本体"]
+                .iter()
+                .any(|k| text.contains(k))
+            {
+                &mut mapping.url
+            } else {
+                continue;
+            };
+            slot.get_or_insert(index);
+        }
+        (mapping.level.is_some() || mapping.title.is_some()).then_some(mapping)
+    }
+}
+
+/// Scrape the charts of an HTML-only table into a [`BmsTable`].
+///
+/// Every row with `<td>` cells and a title or md5 becomes a chart. The synthetic header takes its name from
+/// the page `<title>` and has an empty `data_url`; when every level starts with the same symbol (e.g. "★"),
+/// that symbol becomes the header symbol and is removed from the levels. `level_order` lists the levels in
+/// order of first appearance.
+///
+/// Returns `None` when the page has no element matching [`ColumnMapping::table`].
+#[must_use]
+pub fn scrape_table(html_content: &str, mapping: &ColumnMapping) -> Option<BmsTable> {
+    let document = Html::parse_document(html_content);
+    let table = document
+        .select(&Selector::parse(&mapping.table).ok()?)
+        .next()?;
+
+    let mut charts: Vec<ChartItem> = table_rows(table)
+        .filter(|cells| {
+            cells
+                .iter()
+                .any(|cell| cell.value().name().eq_ignore_ascii_case("td"))
+        })
+        .filter_map(|cells| scrape_chart(&cells, mapping))
+        .collect();
+
+    let symbol = common_symbol(charts.iter().map(|c| c.level.as_str()));
+    let mut level_order: Vec<String> = Vec::new();
+    for chart in &mut charts {
+        if let Some(level) = chart.level.strip_prefix(symbol.as_str()) {
+            chart.level = level.trim_start().to_string();
+        }
+        if !level_order.contains(&chart.level) {
+            level_order.push(chart.level.clone());
+        }
+    }
+
+    let name = Selector::parse("title")
+        .ok()
+        .and_then(|selector| document.select(&selector).next())
+        .map(cell_text)
+        .unwrap_or_default();
+    let header = BmsTableHeader {
+        name,
+        symbol,
+        data_url: String::new(),
+        course: Vec::new(),
+        level_order,
+        tag: None,
+        mode: None,
+        last_update: None,
+        update_url: None,
+        tag_order: None,
+        comment: None,
+        extra: BTreeMap::new(),
+    };
+    Some(BmsTable {
+        header,
+        data: BmsTableData { charts },
+    })
+}
+
+/// Which columns of an HTML table list hold which table fields.
+///
+/// Columns are counted from 0 over the cells of each row; `None` means the list has no such column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListColumnMapping {
+    /// CSS selector of the table; the first matching element is scraped.
+    pub table: String,
+    /// Column holding the table name.
+    pub name: Option<usize>,
+    /// Column holding the table symbol.
+    pub symbol: Option<usize>,
+    /// Column whose first link is the table URL; without it, the first link of the name column, then of the
+    /// row, is used.
+    pub url: Option<usize>,
+    /// Columns copied as strings into [`BmsTableInfo::extra`], with the key to store them under.
+    pub extra: Vec<(String, usize)>,
+}
+
+impl Default for ListColumnMapping {
+    /// The common layout: name, symbol, tag and comment columns, followed by the link.
+    fn default() -> Self {
+        Self {
+            table: "table".to_string(),
+            name: Some(0),
+            symbol: Some(1),
+            url: None,
+            extra: vec![("tag".to_string(), 2), ("comment".to_string(), 3)],
+        }
+    }
+}
+
+impl ListColumnMapping {
+    /// Guess the mapping from the header cells of the first `<table>` of a page.
+    ///
+    /// Header cells are matched by their text, in English or Japanese (e.g. "Name"/"名前", "Symbol"/"記号",
+    /// "URL"/"リンク"); tag, comment and date columns ("Tag"/"タグ", "Comment"/"コメント", "Date"/"更新日")
+    /// become `extra` entries keyed `tag`, `comment` and `date`. Returns `None` when no name column is found.
+    #[must_use]
+    pub fn detect(html_content: &str) -> Option<Self> {
+        let document = Html::parse_document(html_content);
+        let table = document.select(&Selector::parse("table").ok()?).next()?;
+        let header = table_rows(table).find(|cells| {
+            cells
+                .iter()
+                .any(|cell| cell.value().name().eq_ignore_ascii_case("th"))
+        })?;
+        let mut mapping = Self {
+            name: None,
+            symbol: None,
+            extra: Vec::new(),
+            ..Self::default()
+        };
+        for (index, cell) in header.iter().enumerate() {
+            let text = cell_text(*cell).to_lowercase();
+            let matches = |keys: &[&str]| keys.iter().any(|k| text.contains(k));
+            let extra_key = if matches(&["symbol", "記号", "シンボル"]) {
+                mapping.symbol.get_or_insert(index);
+                continue;
+            } else if matches(&["url", "link", "リンク"]) {
+                mapping.url.get_or_insert(index);
+                continue;
+            } else if matches(&["tag", "タグ"]) {
+                "tag"
+            } else if matches(&["comment", "コメント", "説明", "備考"]) {
+                "comment"
+            } else if matches(&["date", "update", "更新", "日付"]) {
+                "date"
+            } else if matches(&["name", "名前", "名称", "難易度表", "table"]) {
+                mapping.name.get_or_insert(index);
+                continue;
+            } else {
+                continue;
+            };
+            if !mapping.extra.iter().any(|(key, _)| key == extra_key) {
+                mapping.extra.push((extra_key.to_string(), index));
+            }
+        }
+        mapping.name.is_some().then_some(mapping)
+    }
+}
+
+/// Scrape an HTML list of tables into a [`BmsTableList`].
+///
+/// Every row with `<td>` cells, a name and a link becomes an entry; links are resolved against `base_url`.
+/// Empty `extra` cells are left out.
+///
+/// Returns `None` when the page has no element matching [`ListColumnMapping::table`].
+#[must_use]
+pub fn scrape_table_list(
+    html_content: &str,
+    base_url: &url::Url,
+    mapping: &ListColumnMapping,
+) -> Option<BmsTableList> {
+    let document = Html::parse_document(html_content);
+    let table = document
+        .select(&Selector::parse(&mapping.table).ok()?)
+        .next()?;
+    let listes = table_rows(table)
+        .filter(|cells| {
+            cells
+                .iter()
+                .any(|cell| cell.value().name().eq_ignore_ascii_case("td"))
+        })
+        .filter_map(|cells| scrape_table_info(&cells, base_url, mapping))
+        .collect();
+    Some(BmsTableList { listes })
+}
+
+/// Read one row of a table list, or `None` when it has no name or no resolvable link.
+fn scrape_table_info(
+    cells: &[ElementRef<'_>],
+    base_url: &url::Url,
+    mapping: &ListColumnMapping,
+) -> Option<BmsTableInfo> {
+    let cell = |column: Option<usize>| column.and_then(|i| cells.get(i)).copied();
+    let name = cell(mapping.name)
+        .map(cell_text)
+        .filter(|s| !s.is_empty())?;
+    let href = cell(mapping.url)
+        .and_then(first_href)
+        .or_else(|| cell(mapping.name).and_then(first_href))
+        .or_else(|| cells.iter().find_map(|c| first_href(*c)))?;
+    let url = base_url.join(&href).ok()?;
+    let extra = mapping
+        .extra
+        .iter()
+        .filter_map(|(key, i)| {
+            let text = cells.get(*i).map(|c| cell_text(*c))?;
+            (!text.is_empty()).then(|| (key.clone(), Value::String(text)))
+        })
+        .collect();
+    Some(BmsTableInfo {
+        name,
+        symbol: cell(mapping.symbol).map(cell_text).unwrap_or_default(),
+        url,
+        extra,
+    })
+}
+
+/// Read one table row into a chart, or `None` when it has neither a title nor an md5.
+fn scrape_chart(cells: &[ElementRef<'_>], mapping: &ColumnMapping) -> Option<ChartItem> {
+    let text = |column: Option<usize>| {
+        column
+            .and_then(|i| cells.get(i))
+            .map(|cell| cell_text(*cell))
+            .filter(|s| !s.is_empty())
+    };
+    let link = |column: Option<usize>| {
+        column
+            .and_then(|i| cells.get(i))
+            .and_then(|c| first_href(*c))
+    };
+    let md5 = mapping.lr2ir.map_or_else(
+        || cells.iter().find_map(|cell| lr2ir_md5(*cell)),
+        |i| cells.get(i).and_then(|cell| lr2ir_md5(*cell)),
+    );
+    let title = text(mapping.title);
+    if title.is_none() && md5.is_none() {
+        return None;
+    }
+    Some(ChartItem {
+        level: text(mapping.level).unwrap_or_else(|| "0".to_string()),
+        md5,
+        sha256: None,
+        title,
+        subtitle: None,
+        artist: text(mapping.artist),
+        subartist: None,
+        url: link(mapping.url),
+        url_diff: link(mapping.url_diff),
+        extra: BTreeMap::new(),
+    })
+}
+
+/// The cells (`<td>` and `<th>`) of each row of a table, skipping nested tables.
+pub(crate) fn table_rows<'a>(
+    table: ElementRef<'a>,
+) -> impl Iterator<Item = Vec<ElementRef<'a>>> + 'a {
+    let rows = Selector::parse("tr")
+        .map(|selector| table.select(&selector).collect::<Vec<_>>())
+        .unwrap_or_default();
+    rows.into_iter()
+        .filter(move |row| {
+            // Rows of a nested table belong to that table.
+            row.ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|e| e.value().name().eq_ignore_ascii_case("table"))
+                == Some(table)
+        })
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| {
+                    let name = cell.value().name();
+                    name.eq_ignore_ascii_case("td") || name.eq_ignore_ascii_case("th")
+                })
+                .collect()
+        })
+}
+
+/// Text of an element with whitespace collapsed.
+pub(crate) fn cell_text(element: ElementRef<'_>) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `href` of the first link inside an element.
+pub(crate) fn first_href(element: ElementRef<'_>) -> Option<String> {
+    let selector = Selector::parse("a[href]").ok()?;
+    element
+        .select(&selector)
+        .find_map(|a| a.value().attr("href"))
+        .map(|href| href.trim().to_string())
+        .filter(|href| !href.is_empty())
+}
+
+/// Chart md5 of the first LR2IR link inside an element, read from its `bmsmd5=` query parameter.
+fn lr2ir_md5(element: ElementRef<'_>) -> Option<Md5> {
+    let selector = Selector::parse("a[href]").ok()?;
+    element.select(&selector).find_map(|a| {
+        let href = a.value().attr("href")?;
+        let start = href.find("bmsmd5=")? + "bmsmd5=".len();
+        let value = href.get(start..)?;
+        let end = value.find(['&', '#']).unwrap_or(value.len());
+        value.get(..end)?.parse().ok()
+    })
+}
+
+/// Symbol shared as a prefix by every level, e.g. "★" for "★1" and "★12".
+///
+/// Only non-alphanumeric leading characters are considered; returns an empty string if there is none.
+fn common_symbol<'a>(levels: impl Iterator<Item = &'a str>) -> String {
+    let mut symbol: Option<&str> = None;
+    for level in levels {
+        let end = level.find(char::is_alphanumeric).unwrap_or(level.len());
+        let prefix = level.get(..end).unwrap_or_default().trim_end();
+        match symbol {
+            None => symbol = Some(prefix),
+            Some(s) if s == prefix => {}
+            Some(_) => return String::new(),
+        }
+    }
+    symbol.unwrap_or_default().to_string()
+}
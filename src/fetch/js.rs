@@ -0,0 +1,397 @@
+//! Minimal JavaScript literal scanning
+//!
+//! Some table pages define their header or chart data as JavaScript instead of linking a JSON file,
+//! e.g. `var header = {...}` in a `<script>` block, and some data files are scripts such as
+//! `callback([...]);`. These helpers locate object and array literals in such source and convert the
+//! JSON-like subset of JavaScript into JSON: unquoted keys, single-quoted and template strings, comments,
+//! trailing commas and `undefined` are accepted.
+
+/// JSON converted from a JavaScript literal by [`to_json`].
+pub(crate) struct Conversion {
+    /// Converted JSON text.
+    pub json: String,
+    /// Trailing commas were removed.
+    pub trailing_commas: bool,
+    /// Comments were removed.
+    pub comments: bool,
+    /// Other JavaScript syntax was converted: unquoted keys, non-JSON quotes or escapes, `undefined`.
+    pub js_syntax: bool,
+}
+
+/// A literal that makes up a whole script, e.g. `callback([...]);` or `var mdata = [...];`.
+pub(crate) struct Wrapped<'a> {
+    /// Source text of the literal, including its outer brackets.
+    pub literal: &'a str,
+    /// The literal is the argument of a call, as in JSONP.
+    pub callback: bool,
+    /// The literal is assigned to a variable or is the default export.
+    pub assignment: bool,
+    /// The statement ends with a semicolon.
+    pub semicolon: bool,
+    /// Comments surround the statement.
+    pub comments: bool,
+}
+
+/// Recognize a script consisting of a single object or array literal, optionally passed to a callback,
+/// assigned to a variable or exported with `export default`, and followed by a semicolon.
+pub(crate) fn unwrap(source: &str) -> Option<Wrapped<'_>> {
+    let bytes = source.as_bytes();
+    let prefix_start = skip_insignificant(bytes, 0);
+    let start = prefix_start
+        + bytes
+            .get(prefix_start..)?
+            .iter()
+            .position(|&b| matches!(b, b'{' | b'['))?;
+    let prefix = source.get(prefix_start..start)?.trim();
+    let (callback, assignment) = if prefix.is_empty() {
+        (false, false)
+    } else if let Some(name) = prefix.strip_suffix('(') {
+        (is_path(name.trim()), false)
+    } else if let Some(name) = prefix.strip_suffix('=') {
+        let name = name.trim();
+        let name = ["var ", "let ", "const "]
+            .iter()
+            .find_map(|keyword| name.strip_prefix(keyword))
+            .unwrap_or(name);
+        (false, is_path(name.trim()))
+    } else {
+        (false, prefix.split_whitespace().eq(["export", "default"]))
+    };
+    if !prefix.is_empty() && !callback && !assignment {
+        return None;
+    }
+
+    let end = literal_end(source, start)?;
+    let mut rest = skip_insignificant(bytes, end);
+    if callback {
+        if bytes.get(rest) != Some(&b')') {
+            return None;
+        }
+        rest = skip_insignificant(bytes, rest + 1);
+    }
+    let semicolon = bytes.get(rest) == Some(&b';');
+    if semicolon {
+        rest = skip_insignificant(bytes, rest + 1);
+    }
+    if rest < bytes.len() {
+        return None;
+    }
+    let comments = skip_whitespace(bytes, 0) != prefix_start
+        || source
+            .get(end..)
+            .is_some_and(|suffix| contains_comment(suffix.as_bytes()));
+    Some(Wrapped {
+        literal: source.get(start..end)?,
+        callback,
+        assignment,
+        semicolon,
+        comments,
+    })
+}
+
+/// Whether `name` is a dotted identifier path such as `callback` or `window.bmsdata`.
+fn is_path(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|part| {
+            part.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'))
+        })
+}
+
+/// Whether a comment starts anywhere outside string literals.
+fn contains_comment(bytes: &[u8]) -> bool {
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        if comment_end(bytes, i).is_some() {
+            return true;
+        }
+        i = if matches!(b, b'"' | b'\'' | b'`') {
+            string_end(bytes, i)
+        } else {
+            i + 1
+        };
+    }
+    false
+}
+
+/// Find every object or array literal directly assigned to a name in `script`, in source order, e.g. the
+/// `{...}` of `var header = {...}` or `window.bmstable = {...}`.
+///
+/// Literals are returned with their outer brackets. Literals nested inside an assigned literal are not
+/// reported separately.
+pub(crate) fn assigned_literals(script: &str) -> Vec<&str> {
+    let bytes = script.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        if let Some(end) = comment_end(bytes, i) {
+            i = end;
+            continue;
+        }
+        match b {
+            b'"' | b'\'' | b'`' => {
+                i = string_end(bytes, i);
+                continue;
+            }
+            b'=' if is_assignment(bytes, i) => {
+                let start = skip_whitespace(bytes, i + 1);
+                if has_target(script, i)
+                    && let Some(end) = literal_end(script, start)
+                    && let Some(literal) = script.get(start..end)
+                {
+                    found.push(literal);
+                    i = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Byte index just past the object or array literal starting at `start`.
+///
+/// Returns `None` when `start` is not an opening bracket or the literal is not closed.
+pub(crate) fn literal_end(s: &str, start: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    if !matches!(bytes.get(start), Some(b'{' | b'[')) {
+        return None;
+    }
+    let mut depth = 0usize;
+    let mut i = start;
+    while let Some(&b) = bytes.get(i) {
+        if let Some(end) = comment_end(bytes, i) {
+            i = end;
+            continue;
+        }
+        match b {
+            b'"' | b'\'' | b'`' => {
+                i = string_end(bytes, i);
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Convert a JavaScript object or array literal into JSON text.
+///
+/// Only the syntax differences listed in the module docs are handled; anything else is copied as is and
+/// left for the JSON parser to reject.
+pub(crate) fn to_json(literal: &str) -> Conversion {
+    let bytes = literal.as_bytes();
+    let mut out = String::with_capacity(literal.len());
+    let mut trailing_commas = false;
+    let mut comments = false;
+    let mut js_syntax = false;
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        if let Some(end) = comment_end(bytes, i) {
+            comments = true;
+            i = end;
+            continue;
+        }
+        match b {
+            b'"' | b'\'' | b'`' => {
+                let end = string_end(bytes, i);
+                let body = literal
+                    .get(i + 1..end.saturating_sub(1))
+                    .unwrap_or_default();
+                let converted = push_json_string(&mut out, body);
+                js_syntax |= converted || b != b'"';
+                i = end;
+            }
+            // Trailing comma before a closing bracket.
+            b',' if matches!(
+                bytes.get(skip_insignificant(bytes, i + 1)),
+                Some(b'}' | b']')
+            ) =>
+            {
+                trailing_commas = true;
+                i += 1;
+            }
+            b if is_word_byte(b) => {
+                let end = bytes
+                    .get(i..)
+                    .and_then(|rest| rest.iter().position(|&c| !is_word_byte(c)))
+                    .map_or(bytes.len(), |p| i + p);
+                let word = literal.get(i..end).unwrap_or_default();
+                let is_key = out.trim_end().ends_with(['{', ','])
+                    && bytes.get(skip_insignificant(bytes, end)) == Some(&b':');
+                if is_key {
+                    push_json_string(&mut out, word);
+                    js_syntax = true;
+                } else if word == "undefined" {
+                    out.push_str("null");
+                    js_syntax = true;
+                } else {
+                    out.push_str(word);
+                }
+                i = end;
+            }
+            _ => {
+                let ch = literal.get(i..).and_then(|rest| rest.chars().next());
+                if let Some(ch) = ch {
+                    out.push(ch);
+                }
+                i += ch.map_or(1, char::len_utf8);
+            }
+        }
+    }
+    Conversion {
+        json: out,
+        trailing_commas,
+        comments,
+        js_syntax,
+    }
+}
+
+/// Append the body of a JavaScript string literal (without its quotes) as a JSON string.
+///
+/// Returns whether the body used escapes that are not valid in JSON.
+fn push_json_string(out: &mut String, body: &str) -> bool {
+    let mut converted = false;
+    out.push('"');
+    let mut chars = body.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(c @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u')) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                Some('x') => {
+                    out.push_str("\\u00");
+                    out.extend(chars.by_ref().take(2));
+                    converted = true;
+                }
+                // Line continuation.
+                Some('\n') | None => converted = true,
+                Some(c) => {
+                    push_json_char(out, c);
+                    converted = true;
+                }
+            },
+            c => push_json_char(out, c),
+        }
+    }
+    out.push('"');
+    converted
+}
+
+/// Append a character to a JSON string, escaping it when required.
+fn push_json_char(out: &mut String, ch: char) {
+    match ch {
+        '"' => out.push_str("\\\""),
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        c if c.is_control() => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+        c => out.push(c),
+    }
+}
+
+/// Whether the `=` at `i` is an assignment rather than part of `==`, `=>`, `<=`, `+=`, etc.
+fn is_assignment(bytes: &[u8], i: usize) -> bool {
+    let prev = i.checked_sub(1).and_then(|p| bytes.get(p));
+    !matches!(bytes.get(i + 1), Some(b'=' | b'>'))
+        && !matches!(
+            prev,
+            Some(
+                b'=' | b'!'
+                    | b'<'
+                    | b'>'
+                    | b'+'
+                    | b'-'
+                    | b'*'
+                    | b'/'
+                    | b'%'
+                    | b'&'
+                    | b'|'
+                    | b'^'
+                    | b'?'
+            )
+        )
+}
+
+/// Whether a name such as `header` or `window.bmstable` immediately precedes the `=` at `i`.
+fn has_target(s: &str, i: usize) -> bool {
+    s.get(..i)
+        .and_then(|head| head.trim_end().chars().next_back())
+        .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'))
+}
+
+/// Whether `b` can be part of an identifier, keyword or number.
+const fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$' | b'.' | b'+' | b'-')
+}
+
+/// Index of the first non-whitespace byte at or after `i`.
+fn skip_whitespace(bytes: &[u8], i: usize) -> usize {
+    bytes
+        .get(i..)
+        .and_then(|rest| rest.iter().position(|b| !b.is_ascii_whitespace()))
+        .map_or(bytes.len(), |p| i + p)
+}
+
+/// Index of the first byte at or after `i` that is neither whitespace nor part of a comment.
+fn skip_insignificant(bytes: &[u8], mut i: usize) -> usize {
+    loop {
+        i = skip_whitespace(bytes, i);
+        match comment_end(bytes, i) {
+            Some(end) => i = end,
+            None => return i,
+        }
+    }
+}
+
+/// Index just past the string literal whose opening quote is at `start`, or the end of the text.
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes.get(start).copied();
+    let mut i = start + 1;
+    while let Some(&b) = bytes.get(i) {
+        if b == b'\\' {
+            i += 2;
+        } else if Some(b) == quote {
+            return i + 1;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Index just past the comment starting at `start`, if a comment starts there.
+fn comment_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let rest = bytes.get(start..)?;
+    if rest.starts_with(b"//") {
+        Some(
+            rest.iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |p| start + p + 1),
+        )
+    } else if rest.starts_with(b"/*") {
+        Some(
+            rest.windows(2)
+                .skip(2)
+                .position(|w| w == b"*/")
+                .map_or(bytes.len(), |p| start + p + 4),
+        )
+    } else {
+        None
+    }
+}
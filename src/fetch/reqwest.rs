@@ -1,11 +1,14 @@
 //! Network fetching module based on `reqwest`
 //!
 //! Provides an all-in-one ability to fetch and parse BMS difficulty tables from a web page or a header JSON source:
-//! - Fetch the page and extract the bmstable header URL from HTML (if present);
-//! - Download and parse the header JSON;
-//! - Download and parse chart data according to `data_url` in the header;
+//! - Fetch the page and extract the bmstable header URL candidates from HTML (if present);
+//! - Download and parse the header JSON, trying the candidates in order until one is a valid header,
+//!   or use the header defined inline in a `<script>` block of the page;
+//! - Download and parse chart data according to `data_url` in the header, unless the page defines it inline;
 //! - Return a parsed `BmsTable` plus the raw JSON strings used for parsing.
 //!
+//! The table resolution itself is done by [`TableResolver`]; [`Fetcher`] only performs its requests.
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -20,20 +23,31 @@
 //! ```
 #![cfg(feature = "reqwest")]
 
-use std::time::Duration;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
-use anyhow::{Context, Result, anyhow};
+use futures_util::{Stream, StreamExt, stream};
 use reqwest::{
-    Client, IntoUrl,
-    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, IntoUrl, Proxy, StatusCode,
+    header::{
+        ACCEPT, ACCEPT_LANGUAGE, CONNECTION, CONTENT_TYPE, ETAG, HeaderMap, HeaderName,
+        HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+        UPGRADE_INSECURE_REQUESTS, USER_AGENT,
+    },
+    redirect::Policy,
 };
-use serde::de::DeserializeOwned;
 
 use crate::{
-    BmsTable, BmsTableData, BmsTableHeader, BmsTableList, BmsTableRaw,
+    BmsTableList,
+    error::{Error, FetchStage, Result},
     fetch::{
-        FetchedTable, FetchedTableList, HeaderQueryContent, TableFetcher,
-        header_query_with_fallback, parse_json_str_with_fallback,
+        CachePolicy, DiskCache, FetchedTable, FetchedTableList, FetchedTables, HttpRequest,
+        HttpResponse, ListFilter, ListedTable, ResolveStep, RetryPolicy, TableFetcher,
+        TableResolver, TableUpdate, TableValidators,
+        cache::CachePlan,
+        encoding::DecodedText,
+        html_table::{ListColumnMapping, scrape_table_list},
+        json_with_fallback,
+        throttle::{HostLimiter, HostLimits},
     },
 };
 
@@ -44,22 +58,45 @@ use crate::{
 pub struct Fetcher {
     /// Underlying HTTP client.
     client: Client,
+    /// Request timeouts overriding the client timeout for some stages.
+    stage_timeouts: BTreeMap<FetchStage, Duration>,
+    /// On-disk cache and how it is used, if any.
+    cache: Option<(DiskCache, CachePolicy)>,
+    /// When failed requests are sent again.
+    retry: RetryPolicy,
+    /// Per-host limits, shared by all clones.
+    hosts: Arc<HostLimiter>,
 }
 
 impl Fetcher {
-    /// Create a fetcher from an existing [`reqwest::Client`].
+    /// Create a fetcher from an existing [`reqwest::Client`], making a single attempt per request and with no
+    /// per-host limits.
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            stage_timeouts: BTreeMap::new(),
+            cache: None,
+            retry: RetryPolicy::none(),
+            hosts: Arc::new(HostLimiter::new(HostLimits::UNLIMITED)),
+        }
+    }
+
+    /// Start configuring a fetcher from secure defaults, see [`FetcherBuilder`].
     #[must_use]
-    pub const fn new(client: Client) -> Self {
-        Self { client }
+    pub fn builder() -> FetcherBuilder {
+        FetcherBuilder::new()
     }
 
     /// Create a fetcher with a more compatible, browser-like HTTP client configuration.
     ///
+    /// Equivalent to `FetcherBuilder::lenient().build()`; note that it accepts invalid TLS certificates.
+    ///
     /// # Errors
     ///
-    /// Returns an error if building the underlying HTTP client fails.
+    /// Returns [`Error::Client`] if building the underlying HTTP client fails.
     pub fn lenient() -> Result<Self> {
-        Ok(Self::new(make_lenient_client()?))
+        FetcherBuilder::lenient().build()
     }
 
     /// Borrow the underlying [`reqwest::Client`].
@@ -72,111 +109,262 @@ impl Fetcher {
     ///
     /// # Errors
     ///
-    /// Returns an error if fetching or parsing the table fails.
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the table fails.
     pub async fn fetch_table(&self, web_url: impl IntoUrl) -> Result<FetchedTable> {
-        let web_url = web_url.into_url().context("When parsing target url")?;
+        let web_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
 
-        let web_page_text = self.fetch_text(web_url.clone(), "web page").await?;
-
-        let (web_header_query, web_used_text) =
-            header_query_with_fallback::<BmsTableHeader>(&web_page_text)
-                .context("When extracting header query from web page")?;
+        match self.resolve(TableResolver::new(web_url.clone())).await? {
+            TableUpdate::Modified(fetched) => Ok(*fetched),
+            TableUpdate::NotModified => Err(unexpected_not_modified(web_url)),
+        }
+    }
 
-        let (header_json_url, header, header_raw) = match web_header_query {
-            HeaderQueryContent::Url(header_url_string) => {
-                let header_json_url = web_url
-                    .join(&header_url_string)
-                    .context("When resolving header json url")?;
+    /// Fetch many tables concurrently, yielding each result as soon as its table is done.
+    ///
+    /// At most `concurrency` tables (at least 1) are fetched at the same time, and results come in completion
+    /// order, each with the URL it was fetched from. The stream owns a clone of the fetcher and spawns no tasks:
+    /// dropping it cancels the fetches in flight.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// use bms_table::fetch::reqwest::Fetcher;
+    /// use futures_util::StreamExt;
+    ///
+    /// let fetcher = Fetcher::lenient()?;
+    /// let urls = vec![
+    ///     url::Url::parse("https://stellabms.xyz/sl/table.html")?,
+    ///     url::Url::parse("https://stellabms.xyz/st/table.html")?,
+    /// ];
+    /// let mut results = fetcher.fetch_tables(urls, 4);
+    /// while let Some((url, result)) = results.next().await {
+    ///     match result {
+    ///         Ok(fetched) => println!("{url}: {} charts", fetched.table.data.charts.len()),
+    ///         Err(e) => println!("{url}: {e}"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fetch_tables<I>(
+        &self,
+        urls: I,
+        concurrency: usize,
+    ) -> impl Stream<Item = (reqwest::Url, Result<FetchedTable>)> + Send + 'static
+    where
+        I: IntoIterator<Item = reqwest::Url>,
+        I::IntoIter: Send + 'static,
+    {
+        let fetcher = Arc::new(self.clone());
+        stream::iter(urls)
+            .map(move |url| {
+                let fetcher = Arc::clone(&fetcher);
+                async move {
+                    let result = fetcher.fetch_table(url.clone()).await;
+                    (url, result)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
 
-                let header_text = self
-                    .fetch_text(header_json_url.clone(), "header json")
-                    .await?;
+    /// Fetch a table list, then every table it references that `filter` selects.
+    ///
+    /// See [`Fetcher::fetch_listed_tables`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the list fails; failures of
+    /// single tables are reported in [`FetchedTables::tables`] instead.
+    pub async fn fetch_all_tables(
+        &self,
+        list_url: impl IntoUrl,
+        filter: &ListFilter,
+        concurrency: usize,
+    ) -> Result<FetchedTables> {
+        let list = self.fetch_table_list(list_url).await?;
+        Ok(self
+            .fetch_listed_tables(
+                BmsTableList {
+                    listes: list.tables,
+                },
+                filter,
+                concurrency,
+            )
+            .await)
+    }
 
-                let (header_query2, header_used_text) =
-                    header_query_with_fallback::<BmsTableHeader>(&header_text)
-                        .context("When parsing header json")?;
+    /// Fetch every table of `list` that `filter` selects, at most `concurrency` at a time (at least 1).
+    ///
+    /// Each selected entry gets its fetched table or its error, in list order; the entries left out are kept
+    /// in [`FetchedTables::skipped`]. [`FetchedTables::summary`] counts them.
+    pub async fn fetch_listed_tables(
+        &self,
+        list: BmsTableList,
+        filter: &ListFilter,
+        concurrency: usize,
+    ) -> FetchedTables {
+        let (selected, skipped): (Vec<_>, Vec<_>) = list
+            .listes
+            .into_iter()
+            .partition(|info| filter.matches(info));
+        let tables = stream::iter(selected)
+            .map(|info| async move {
+                let result = self.fetch_table(info.url.clone()).await;
+                ListedTable { info, result }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        FetchedTables { tables, skipped }
+    }
 
-                let HeaderQueryContent::Value(header) = header_query2 else {
-                    return Err(anyhow!(
-                        "Cycled header found. web_url: {web_url}, header_url: {header_url_string}"
-                    ));
-                };
+    /// Fetch a table again, unless it did not change since `validators` were recorded.
+    ///
+    /// The validators of each stage (usually [`FetchedTable::validators`] of the previous fetch) are sent as
+    /// `If-None-Match`/`If-Modified-Since`; [`TableUpdate::NotModified`] is returned when neither the header nor
+    /// the data changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the table fails.
+    pub async fn fetch_table_if_modified(
+        &self,
+        web_url: impl IntoUrl,
+        validators: &TableValidators,
+    ) -> Result<TableUpdate> {
+        let web_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
 
-                (header_json_url, header, header_used_text)
-            }
-            HeaderQueryContent::Value(header) => (web_url, header, web_used_text),
-        };
+        self.resolve(TableResolver::with_validators(web_url, validators.clone()))
+            .await
+    }
 
-        let data_json_url = header_json_url
-            .join(&header.data_url)
-            .context("When resolving data json url")?;
+    /// Fetch a list of BMS difficulty tables.
+    ///
+    /// The list is read as a JSON array; when the body is not JSON, it is scraped as an HTML table with
+    /// [`scrape_table_list`] and `raw_json` holds the scraped list serialized as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage if fetching or parsing the list fails.
+    pub async fn fetch_table_list(&self, web_url: impl IntoUrl) -> Result<FetchedTableList> {
+        let list_url = web_url.into_url().map_err(|e| Error::InvalidUrl {
+            source: Box::new(e),
+        })?;
 
-        let (data, data_raw) = self
-            .fetch_json_with_fallback::<BmsTableData>(
-                data_json_url.clone(),
-                "data json",
-                "data json",
-            )
+        let text = self
+            .fetch_text(list_url.clone(), FetchStage::TableList)
             .await?;
+        table_list_from_text(list_url, &text)
+    }
 
-        Ok(FetchedTable {
-            table: BmsTable { header, data },
-            raw: BmsTableRaw {
-                header_json_url,
-                header_raw,
-                data_json_url,
-                data_raw,
-            },
-        })
+    /// Fetch a URL and decode its body, classifying failures by stage.
+    ///
+    /// The encoding is detected with [`decode_text`](crate::fetch::encoding::decode_text) rather than trusting
+    /// the `Content-Type` header alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Network`] if the request fails or the body cannot be read,
+    /// and [`Error::Status`] if the server answers with a non-success status.
+    async fn fetch_text(&self, url: reqwest::Url, stage: FetchStage) -> Result<DecodedText> {
+        let request = HttpRequest::new(url, stage);
+        self.fetch(&request, &request.url)
+            .await?
+            .into_text(&request)
     }
 
-    /// Fetch a list of BMS difficulty tables.
+    /// Drive a [`TableResolver`] until it finishes.
     ///
     /// # Errors
     ///
-    /// Returns an error if fetching or parsing the list fails.
-    pub async fn fetch_table_list(&self, web_url: impl IntoUrl) -> Result<FetchedTableList> {
-        let list_url = web_url.into_url().context("When parsing table list url")?;
+    /// Returns the [`Error`] that ended the resolution.
+    async fn resolve(&self, mut resolver: TableResolver) -> Result<TableUpdate> {
+        let table_url = resolver.request().url.clone();
+        loop {
+            let step = match self.fetch(resolver.request(), &table_url).await {
+                Ok(response) => resolver.respond(response)?,
+                Err(error) => resolver.fail(error)?,
+            };
+            match step {
+                ResolveStep::Fetch(next) => resolver = next,
+                ResolveStep::Done(fetched) => return Ok(TableUpdate::Modified(fetched)),
+                ResolveStep::NotModified => return Ok(TableUpdate::NotModified),
+            }
+        }
+    }
 
-        let (list, raw_used) = self
-            .fetch_json_with_fallback::<BmsTableList>(list_url, "table list", "table list json")
-            .await?;
-        Ok(FetchedTableList {
-            tables: list.listes,
-            raw_json: raw_used,
-        })
+    /// Answer a [`HttpRequest`] of the table fetched from `table_url`, through the cache if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotCached`] when offline, and the errors of [`Fetcher::send`].
+    async fn fetch(&self, request: &HttpRequest, table_url: &reqwest::Url) -> Result<HttpResponse> {
+        let Some((cache, policy)) = &self.cache else {
+            return self.send(request).await;
+        };
+        match cache.plan(request, policy, policy.ttl_for(table_url))? {
+            CachePlan::Serve(response) => Ok(response),
+            CachePlan::Fetch {
+                request: sent,
+                cached,
+            } => {
+                let result = self.send(&sent).await;
+                cache.complete(request, cached, result, policy)
+            }
+        }
     }
 
-    /// Fetch a URL as text, attaching contextual error messages.
+    /// Perform a [`HttpRequest`], retrying it according to the [`RetryPolicy`], and collect the last response,
+    /// whatever its status.
     ///
     /// # Errors
     ///
-    /// Returns an error if the request fails or the body cannot be read as text.
-    async fn fetch_text(&self, url: reqwest::Url, fetch_ctx: &'static str) -> Result<String> {
-        self.client
-            .get(url)
-            .send()
-            .await
-            .with_context(|| format!("When fetching {fetch_ctx}"))?
-            .text()
-            .await
-            .with_context(|| format!("When reading {fetch_ctx} body"))
+    /// Returns [`Error::Network`] if the last attempt fails or its body cannot be read.
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let mut attempt = 1;
+        loop {
+            let outcome = self.send_once(request).await;
+            let Some(delay) = self.retry.retry_delay(attempt, &outcome) else {
+                return outcome;
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 
-    /// Fetch a URL and parse JSON with a control-character cleaning fallback.
+    /// Perform one attempt of a [`HttpRequest`] once the per-host limits allow it.
     ///
     /// # Errors
     ///
-    /// Returns an error if fetching fails, or the response cannot be parsed as JSON.
-    async fn fetch_json_with_fallback<T: DeserializeOwned>(
-        &self,
-        url: reqwest::Url,
-        fetch_ctx: &'static str,
-        parse_ctx: &'static str,
-    ) -> Result<(T, String)> {
-        let text = self.fetch_text(url, fetch_ctx).await?;
-        parse_json_str_with_fallback::<T>(&text)
-            .with_context(|| format!("When parsing {parse_ctx}"))
+    /// Returns [`Error::Network`] if the request fails or the body cannot be read.
+    async fn send_once(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let HttpRequest { url, stage, .. } = request;
+        let _permit = self.hosts.acquire(url).await;
+        let mut builder = self
+            .client
+            .get(url.clone())
+            .headers(conditional_headers(request));
+        if let Some(timeout) = self.stage_timeouts.get(stage) {
+            builder = builder.timeout(*timeout);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| network_error(*stage, url, e))?;
+        let status = response.status();
+        let final_url = response.url().clone();
+        let headers = response.headers().clone();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| network_error(*stage, url, e))?;
+        Ok(http_response(final_url, status, &headers, bytes.to_vec()))
     }
 }
 
@@ -190,52 +378,426 @@ impl TableFetcher for Fetcher {
     }
 }
 
-/// Create a more lenient and compatible HTTP client.
+/// Builder for a [`Fetcher`] with explicit TLS, timeout, proxy and header settings.
+///
+/// [`FetcherBuilder::new`] starts from secure defaults: certificates and hostnames are verified, requests time
+/// out after 60 seconds, at most 10 redirects are followed, cookies are not stored and the user agent is
+/// `bms-table-rs/<version>`. [`FetcherBuilder::lenient`] is the browser-like preset used by [`Fetcher::lenient`].
+///
+/// # Example
 ///
-/// - Set a browser-like UA;
-/// - Configure timeouts and redirects;
-/// - Accept invalid certificates (for a few non-compliant sites);
-/// - Accept invalid hostnames (for a few non-compliant sites);
+/// ```rust
+/// use std::{collections::BTreeMap, sync::Arc, time::Duration};
 ///
-/// Note: use `danger_accept_invalid_certs` with caution in production.
+/// use bms_table::{error::FetchStage, fetch::reqwest::FetcherBuilder};
+///
+/// let fetcher = FetcherBuilder::new()
+///     .timeout(Duration::from_secs(20))
+///     .stage_timeout(FetchStage::Data, Duration::from_secs(120))
+///     .accept_language("ja,en;q=0.8")
+///     .build()?;
+/// # Ok::<(), bms_table::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FetcherBuilder {
+    /// Whether invalid TLS certificates are accepted.
+    accept_invalid_certs: bool,
+    /// Whether TLS certificates not matching the hostname are accepted.
+    accept_invalid_hostnames: bool,
+    /// Timeout of a whole request, unless overridden for its stage.
+    timeout: Option<Duration>,
+    /// Timeout of the connect phase.
+    connect_timeout: Option<Duration>,
+    /// Request timeouts overriding `timeout` for some stages.
+    stage_timeouts: BTreeMap<FetchStage, Duration>,
+    /// Proxy URL used for all requests.
+    proxy: Option<String>,
+    /// `User-Agent` header.
+    user_agent: String,
+    /// `Accept-Language` header.
+    accept_language: Option<String>,
+    /// Maximum number of redirects followed.
+    max_redirects: usize,
+    /// Extra headers sent with every request.
+    headers: HeaderMap,
+    /// Whether cookies are stored and sent back.
+    cookie_store: bool,
+    /// On-disk cache and how it is used, if any.
+    cache: Option<(DiskCache, CachePolicy)>,
+    /// When failed requests are sent again.
+    retry: RetryPolicy,
+    /// Per-host concurrency and rate limits.
+    host_limits: HostLimits,
+}
+
+impl Default for FetcherBuilder {
+    fn default() -> Self {
+        Self {
+            accept_invalid_certs: false,
+            accept_invalid_hostnames: false,
+            timeout: Some(Duration::from_secs(60)),
+            connect_timeout: None,
+            stage_timeouts: BTreeMap::new(),
+            proxy: None,
+            user_agent: concat!("bms-table-rs/", env!("CARGO_PKG_VERSION")).to_string(),
+            accept_language: None,
+            max_redirects: 10,
+            headers: HeaderMap::new(),
+            cookie_store: false,
+            cache: None,
+            retry: RetryPolicy::none(),
+            host_limits: HostLimits::UNLIMITED,
+        }
+    }
+}
+
+impl FetcherBuilder {
+    /// Create a builder with secure defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder matching the browser-like behavior of [`Fetcher::lenient`].
+    ///
+    /// - Send a Chrome user agent, `Accept-Language: zh-CN` and browser-like `Accept` headers;
+    /// - Time out after 60 seconds and follow up to 100 redirects;
+    /// - Store cookies;
+    /// - Accept invalid certificates and hostnames (for a few non-compliant sites).
+    ///
+    /// Note: accepting invalid certificates should be used with caution in production.
+    #[must_use]
+    pub fn lenient() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static(
+                "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9",
+            ),
+        );
+        headers.insert(UPGRADE_INSECURE_REQUESTS, HeaderValue::from_static("1"));
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        Self {
+            accept_invalid_certs: true,
+            accept_invalid_hostnames: true,
+            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119 Safari/537.36 bms-table-rs".to_string(),
+            accept_language: Some("zh-CN,zh;q=0.9,en;q=0.8".to_string()),
+            max_redirects: 100,
+            headers,
+            cookie_store: true,
+            ..Self::default()
+        }
+    }
+
+    /// Accept invalid TLS certificates and certificates not matching the hostname.
+    ///
+    /// Shorthand for [`FetcherBuilder::accept_invalid_certs`] and [`FetcherBuilder::accept_invalid_hostnames`].
+    #[must_use]
+    pub const fn lenient_tls(self, lenient: bool) -> Self {
+        self.accept_invalid_certs(lenient)
+            .accept_invalid_hostnames(lenient)
+    }
+
+    /// Accept invalid TLS certificates, e.g. expired or self-signed ones.
+    #[must_use]
+    pub const fn accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Accept TLS certificates that do not match the hostname.
+    #[must_use]
+    pub const fn accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.accept_invalid_hostnames = accept;
+        self
+    }
+
+    /// Set the timeout of a whole request, or `None` to wait indefinitely.
+    #[must_use]
+    pub fn timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    /// Set the timeout of the connect phase of a request.
+    #[must_use]
+    pub const fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the request timeout of one stage, overriding [`FetcherBuilder::timeout`].
+    ///
+    /// Useful for large chart data files, which may need more time than the web page and header.
+    #[must_use]
+    pub fn stage_timeout(mut self, stage: FetchStage, timeout: Duration) -> Self {
+        self.stage_timeouts.insert(stage, timeout);
+        self
+    }
+
+    /// Send all requests through a proxy, e.g. `http://127.0.0.1:8080` or `socks5h://127.0.0.1:1080`.
+    ///
+    /// SOCKS proxies require the `socks` feature.
+    #[must_use]
+    pub fn proxy(mut self, proxy_url: impl Into<String>) -> Self {
+        self.proxy = Some(proxy_url.into());
+        self
+    }
+
+    /// Set the `User-Agent` header.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Set the `Accept-Language` header, e.g. `ja,en;q=0.8`.
+    #[must_use]
+    pub fn accept_language(mut self, accept_language: impl Into<String>) -> Self {
+        self.accept_language = Some(accept_language.into());
+        self
+    }
+
+    /// Set the maximum number of redirects followed; with `0`, a redirect response fails with [`Error::Status`].
+    #[must_use]
+    pub const fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Add a header sent with every request, replacing a previous value of the same header.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Store cookies set by the servers and send them back.
+    #[must_use]
+    pub const fn cookie_store(mut self, enable: bool) -> Self {
+        self.cookie_store = enable;
+        self
+    }
+
+    /// Keep fetched documents in `cache`, used according to `policy`.
+    #[must_use]
+    pub fn cache(mut self, cache: DiskCache, policy: CachePolicy) -> Self {
+        self.cache = Some((cache, policy));
+        self
+    }
+
+    /// Set when failed requests are sent again; see [`RetryPolicy`]. By default, every request is attempted once.
+    #[must_use]
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run at most `max` requests against one host at the same time (at least 1), across all clones of the
+    /// fetcher.
+    #[must_use]
+    pub const fn max_concurrent_per_host(mut self, max: usize) -> Self {
+        self.host_limits.max_concurrent = Some(max);
+        self
+    }
+
+    /// Start two requests to one host at least `delay` apart, across all clones of the fetcher.
+    #[must_use]
+    pub const fn min_delay_per_host(mut self, delay: Duration) -> Self {
+        self.host_limits.min_delay = delay;
+        self
+    }
+
+    /// Build the fetcher.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Client`] if a header value or the proxy URL is invalid, or building the HTTP client fails.
+    pub fn build(self) -> Result<Fetcher> {
+        let (headers, proxy) = self.validate()?;
+        let mut builder = configure_client!(self, Client::builder(), headers);
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        Ok(Fetcher {
+            client: builder.build().map_err(client_error)?,
+            stage_timeouts: self.stage_timeouts,
+            cache: self.cache,
+            retry: self.retry,
+            hosts: Arc::new(HostLimiter::new(self.host_limits)),
+        })
+    }
+
+    /// Build a synchronous fetcher with the same settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Client`] if a header value or the proxy URL is invalid, or building the HTTP client fails.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::fetch::blocking::Fetcher> {
+        let (headers, proxy) = self.validate()?;
+        let mut builder = configure_client!(self, reqwest::blocking::Client::builder(), headers)
+            .timeout(self.timeout);
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        Ok(crate::fetch::blocking::Fetcher::from_parts(
+            builder.build().map_err(client_error)?,
+            self.stage_timeouts,
+            self.cache,
+            self.retry,
+            HostLimiter::new(self.host_limits),
+        ))
+    }
+
+    /// Collect the default headers and parse the proxy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Client`] if a header value or the proxy URL is invalid.
+    fn validate(&self) -> Result<(HeaderMap, Option<Proxy>)> {
+        let mut headers = self.headers.clone();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(&self.user_agent).map_err(client_error)?,
+        );
+        if let Some(accept_language) = &self.accept_language {
+            headers.insert(
+                ACCEPT_LANGUAGE,
+                HeaderValue::from_str(accept_language).map_err(client_error)?,
+            );
+        }
+        let proxy = self
+            .proxy
+            .as_deref()
+            .map(Proxy::all)
+            .transpose()
+            .map_err(client_error)?;
+        Ok((headers, proxy))
+    }
+}
+
+/// Apply the settings shared by the async and blocking clients of a [`FetcherBuilder`].
+macro_rules! configure_client {
+    ($settings:expr, $builder:expr, $headers:expr) => {{
+        let mut builder = $builder
+            .default_headers($headers)
+            .redirect(redirect_policy($settings.max_redirects))
+            // Automatically include Referer on redirects, closer to browser behavior
+            .referer(true)
+            .cookie_store($settings.cookie_store)
+            .danger_accept_invalid_certs($settings.accept_invalid_certs)
+            .danger_accept_invalid_hostnames($settings.accept_invalid_hostnames);
+        if let Some(timeout) = $settings.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder
+    }};
+}
+use configure_client;
+
+/// Follow up to `max_redirects` redirects; with `0`, the redirect response itself is returned.
+fn redirect_policy(max_redirects: usize) -> Policy {
+    if max_redirects == 0 {
+        Policy::none()
+    } else {
+        Policy::limited(max_redirects)
+    }
+}
+
+/// Convert a client configuration failure into [`Error::Client`].
+fn client_error(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Client {
+        source: Box::new(e),
+    }
+}
+
+/// Parse a fetched table list, scraping it as an HTML table when it is not JSON.
 ///
 /// # Errors
 ///
-/// Returns an error when building the HTTP client fails.
-fn make_lenient_client() -> Result<Client> {
+/// Returns [`Error::TableListJson`] when the text is neither a JSON list nor an HTML table of tables.
+pub(crate) fn table_list_from_text(
+    list_url: reqwest::Url,
+    text: &DecodedText,
+) -> Result<FetchedTableList> {
+    let (list, raw_used) = match json_with_fallback::<BmsTableList>(&text.text) {
+        Ok(parsed) => parsed,
+        Err(source) => {
+            // Not JSON: the list may be published as an HTML table instead.
+            let mapping = ListColumnMapping::detect(&text.text).unwrap_or_default();
+            let list = scrape_table_list(&text.text, &list_url, &mapping)
+                .filter(|list| !list.listes.is_empty())
+                .ok_or(Error::TableListJson {
+                    url: Some(list_url),
+                    source,
+                })?;
+            let raw = serde_json::to_string(&list).unwrap_or_default();
+            (list, raw)
+        }
+    };
+    Ok(FetchedTableList {
+        tables: list.listes,
+        raw_json: raw_used,
+        encoding: text.encoding.to_string(),
+    })
+}
+
+/// Request headers carrying the validators of a conditional [`HttpRequest`].
+pub(crate) fn conditional_headers(request: &HttpRequest) -> HeaderMap {
     let mut headers = HeaderMap::new();
-    headers.insert(
-        HeaderName::from_static("accept"),
-        HeaderValue::from_static(
-            "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9",
-        ),
-    );
-    headers.insert(
-        HeaderName::from_static("accept-language"),
-        HeaderValue::from_static("zh-CN,zh;q=0.9,en;q=0.8"),
-    );
-    headers.insert(
-        HeaderName::from_static("upgrade-insecure-requests"),
-        HeaderValue::from_static("1"),
-    );
-    headers.insert(
-        HeaderName::from_static("connection"),
-        HeaderValue::from_static("keep-alive"),
-    );
-
-    let client = Client::builder()
-        .default_headers(headers)
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119 Safari/537.36 bms-table-rs")
-        .timeout(Duration::from_secs(60))
-        .redirect(reqwest::redirect::Policy::limited(100))
-        // Automatically include Referer on redirects, closer to browser behavior
-        .referer(true)
-        // Enable cookie store, closer to real user sessions
-        .cookie_store(true)
-        // Keep lenient TLS settings for compatibility with some non-compliant sites
-        .danger_accept_invalid_certs(true)
-        .danger_accept_invalid_hostnames(true)
-        .build()
-        .context("When building client")?;
-    Ok(client)
+    for (name, value) in [
+        (IF_NONE_MATCH, &request.if_none_match),
+        (IF_MODIFIED_SINCE, &request.if_modified_since),
+    ] {
+        // Validators come from response headers, so they are valid header values.
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers
+}
+
+/// Collect a received response into an [`HttpResponse`] for the [`TableResolver`].
+pub(crate) fn http_response(
+    url: reqwest::Url,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: Vec<u8>,
+) -> HttpResponse {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let mut response = HttpResponse::new(url, status.as_u16(), body);
+    response.content_type = header(CONTENT_TYPE);
+    response.etag = header(ETAG);
+    response.last_modified = header(LAST_MODIFIED);
+    response.retry_after = header(RETRY_AFTER);
+    response
+}
+
+/// Error for a `304 Not Modified` answer to a fetch that sent no validators.
+pub(crate) const fn unexpected_not_modified(web_url: reqwest::Url) -> Error {
+    Error::Status {
+        stage: FetchStage::WebPage,
+        url: web_url,
+        status: 304,
+    }
+}
+
+/// Convert a [`reqwest::Error`] into [`Error::Network`], classifying whether it is transient.
+pub(crate) fn network_error(stage: FetchStage, url: &reqwest::Url, e: reqwest::Error) -> Error {
+    let transient = e.is_timeout() || e.is_connect() || e.is_request() || e.is_body();
+    Error::Network {
+        stage,
+        url: url.clone(),
+        transient,
+        source: Box::new(e),
+    }
 }
@@ -0,0 +1,744 @@
+//! IO-free table resolution
+//!
+//! [`TableResolver`] runs the "web page → header JSON → chart data" pipeline as a state machine that never
+//! touches the network itself: it asks for one URL at a time with a [`HttpRequest`], and the caller answers
+//! with the [`HttpResponse`] it got from whatever HTTP stack it uses. This lets the same resolution logic run
+//! on `reqwest`, a blocking client, a WASM `fetch`, or recorded fixtures in tests.
+//!
+//! Header candidates, inline headers and data, encoding detection and JSON repair behave exactly as in
+//! the `reqwest` fetcher, which is a thin driver over this module.
+//!
+//! Given the [`TableValidators`] of a previous fetch, [`TableResolver::with_validators`] makes the requests
+//! conditional and finishes with [`ResolveStep::NotModified`] when neither the header nor the data changed.
+//! A `304 Not Modified` web page is skipped over to the previous header URL, and a document that must be
+//! read after all (e.g. the header, when only the data changed) is requested again without validators.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::fetch::{HttpResponse, ResolveStep, TableResolver};
+//!
+//! let page = r#"<meta name="bmstable" content="header.json">"#;
+//! let header = r#"{"name": "Table", "symbol": "t", "data_url": "data.json"}"#;
+//! let data = r#"[{"level": "1", "title": "Song"}]"#;
+//!
+//! let url = url::Url::parse("https://example.com/table.html")?;
+//! let mut resolver = TableResolver::new(url);
+//! let fetched = loop {
+//!     let request = resolver.request();
+//!     let body = match request.url.path() {
+//!         "/table.html" => page,
+//!         "/header.json" => header,
+//!         _ => data,
+//!     };
+//!     let response = HttpResponse::new(request.url.clone(), 200, body.as_bytes().to_vec());
+//!     match resolver.respond(response)? {
+//!         ResolveStep::Fetch(next) => resolver = next,
+//!         ResolveStep::Done(fetched) => break fetched,
+//!         ResolveStep::NotModified => unreachable!("no validators were given"),
+//!     }
+//! };
+//! assert_eq!(fetched.table.header.name, "Table");
+//! assert_eq!(fetched.table.data.charts.len(), 1);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::VecDeque;
+
+use url::Url;
+
+use crate::{
+    BmsTable, BmsTableData, BmsTableHeader, BmsTableRaw,
+    error::{Error, FetchStage, Result},
+    fetch::{
+        FetchedTable, HeaderAttempt, HeaderCandidate, HeaderQueryContent, InlineTable,
+        TableValidators, Validators,
+        encoding::{DecodedText, decode_text},
+        extract_header_candidates, header_query_with_fallback, json_with_fallback,
+        try_extract_bmstable_from_html,
+    },
+};
+
+/// HTTP status of a response to a conditional request whose document did not change.
+const NOT_MODIFIED: u16 = 304;
+
+/// A URL the driver of a [`TableResolver`] must fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HttpRequest {
+    /// URL to `GET`.
+    pub url: Url,
+    /// Stage of the pipeline the request belongs to.
+    pub stage: FetchStage,
+    /// Value for the `If-None-Match` request header, if any.
+    pub if_none_match: Option<String>,
+    /// Value for the `If-Modified-Since` request header, if any.
+    pub if_modified_since: Option<String>,
+}
+
+impl HttpRequest {
+    /// Create an unconditional request.
+    pub(crate) const fn new(url: Url, stage: FetchStage) -> Self {
+        Self {
+            url,
+            stage,
+            if_none_match: None,
+            if_modified_since: None,
+        }
+    }
+
+    /// Whether the request carries validators, so that the server may answer `304 Not Modified`.
+    #[must_use]
+    pub const fn is_conditional(&self) -> bool {
+        self.if_none_match.is_some() || self.if_modified_since.is_some()
+    }
+}
+
+/// Response to a [`HttpRequest`], as received by the driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HttpResponse {
+    /// Final URL after redirects; relative references in the body are resolved against it.
+    pub url: Url,
+    /// HTTP status code.
+    pub status: u16,
+    /// Value of the `Content-Type` header, if any.
+    pub content_type: Option<String>,
+    /// Value of the `ETag` header, if any.
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header, if any.
+    pub last_modified: Option<String>,
+    /// Value of the `Retry-After` header, if any.
+    pub retry_after: Option<String>,
+    /// Raw response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Create a response without headers.
+    #[must_use]
+    pub const fn new(url: Url, status: u16, body: Vec<u8>) -> Self {
+        Self {
+            url,
+            status,
+            content_type: None,
+            etag: None,
+            last_modified: None,
+            retry_after: None,
+            body,
+        }
+    }
+
+    /// Set the `Content-Type` header used for charset detection.
+    #[must_use]
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set the `ETag` header recorded in [`TableValidators`].
+    #[must_use]
+    pub fn with_etag(mut self, etag: impl Into<String>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
+    /// Set the `Last-Modified` header recorded in [`TableValidators`].
+    #[must_use]
+    pub fn with_last_modified(mut self, last_modified: impl Into<String>) -> Self {
+        self.last_modified = Some(last_modified.into());
+        self
+    }
+
+    /// Set the `Retry-After` header, which tells how long to wait before retrying a failed request.
+    #[must_use]
+    pub fn with_retry_after(mut self, retry_after: impl Into<String>) -> Self {
+        self.retry_after = Some(retry_after.into());
+        self
+    }
+
+    /// Validators of the document requested by `request`.
+    fn validators(&self, request: &HttpRequest) -> Validators {
+        Validators {
+            url: request.url.clone(),
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        }
+    }
+
+    /// Check the status and decode the body with [`decode_text`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Status`] if the status is not a success (2xx) status.
+    pub(crate) fn into_text(self, request: &HttpRequest) -> Result<DecodedText> {
+        if !(200..300).contains(&self.status) {
+            return Err(Error::Status {
+                stage: request.stage,
+                url: request.url.clone(),
+                status: self.status,
+            });
+        }
+        Ok(decode_text(&self.body, self.content_type.as_deref()))
+    }
+}
+
+/// Outcome of feeding a response to a [`TableResolver`].
+pub enum ResolveStep {
+    /// Another URL must be fetched; answer [`TableResolver::request`] on the returned resolver.
+    Fetch(TableResolver),
+    /// The table is resolved.
+    Done(Box<FetchedTable>),
+    /// Neither the header nor the data changed since the validators given to
+    /// [`TableResolver::with_validators`] were recorded.
+    NotModified,
+}
+
+/// IO-free state machine resolving a table from its web page URL.
+///
+/// Every call to [`TableResolver::respond`] or [`TableResolver::fail`] consumes the resolver and either returns
+/// the resolver for the next request, the finished table, or the error that ended the resolution.
+pub struct TableResolver {
+    /// The request awaiting a response.
+    request: HttpRequest,
+    /// What the pending request is for.
+    state: Box<State>,
+    /// Validators of the previous and the current fetch.
+    context: Box<Context>,
+}
+
+/// Progress of a [`TableResolver`].
+enum State {
+    /// Waiting for the web page given by the caller.
+    WebPage,
+    /// Waiting for a header candidate of the web page.
+    Header(Candidates),
+    /// Waiting for the previous header, after the web page was not modified.
+    PreviousHeader,
+    /// Waiting for the chart data of a header, or of the unchanged header of the previous fetch (`None`).
+    Data(Option<Box<FoundHeader>>),
+    /// Waiting for the unmodified header again, because the data it references changed.
+    HeaderForData(FetchedData),
+}
+
+/// Validators a resolution starts from and the ones it records.
+struct Context {
+    /// Web page URL given by the caller.
+    web_url: Url,
+    /// Validators of the previous fetch.
+    previous: TableValidators,
+    /// Validators recorded so far.
+    current: TableValidators,
+}
+
+/// Header candidates of a web page still being tried.
+struct Candidates {
+    /// Final URL of the web page, reported by [`Error::HeaderCandidates`].
+    web_url: Url,
+    /// Candidate being fetched.
+    current: HeaderCandidate,
+    /// Candidates not tried yet.
+    remaining: VecDeque<HeaderCandidate>,
+    /// Resolved URLs already requested.
+    tried: Vec<Url>,
+    /// Candidates rejected so far.
+    attempts: Vec<HeaderAttempt>,
+}
+
+/// A header found through a candidate or in the web page itself.
+struct FoundHeader {
+    /// URL the header was read from.
+    url: Url,
+    /// Parsed header.
+    header: BmsTableHeader,
+    /// Raw header JSON used for parsing.
+    raw: String,
+    /// Encoding the header was decoded with.
+    encoding: &'static str,
+    /// Candidates rejected before the header was found.
+    attempts: Vec<HeaderAttempt>,
+}
+
+/// Chart data read before its header.
+struct FetchedData {
+    /// URL the data was read from.
+    url: Url,
+    /// Parsed data.
+    data: BmsTableData,
+    /// Raw data JSON used for parsing.
+    raw: String,
+    /// Encoding the data was decoded with.
+    encoding: &'static str,
+}
+
+impl TableResolver {
+    /// Start resolving the table whose web page (or header JSON) is at `web_url`.
+    #[must_use]
+    pub fn new(web_url: Url) -> Self {
+        Self::with_validators(web_url, TableValidators::default())
+    }
+
+    /// Start resolving a table again, sending the validators recorded by a previous fetch.
+    ///
+    /// Validators are only sent to the URL they were recorded for.
+    #[must_use]
+    pub fn with_validators(web_url: Url, validators: TableValidators) -> Self {
+        Context {
+            web_url: web_url.clone(),
+            previous: validators,
+            current: TableValidators::default(),
+        }
+        .fetch(State::WebPage, web_url, FetchStage::WebPage)
+    }
+
+    /// The URL to fetch next.
+    #[must_use]
+    pub const fn request(&self) -> &HttpRequest {
+        &self.request
+    }
+
+    /// Feed the response to [`TableResolver::request`].
+    ///
+    /// A non-success status is an error for the web page and the data, and a rejected candidate for a header.
+    /// `304 Not Modified` is only accepted in response to a conditional request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] describing the failed stage when the table cannot be resolved.
+    pub fn respond(self, response: HttpResponse) -> Result<ResolveStep> {
+        let Self {
+            request,
+            state,
+            mut context,
+        } = self;
+        if response.status == NOT_MODIFIED && request.is_conditional() {
+            return Ok(context.not_modified(*state, &request, &response));
+        }
+        match *state {
+            State::WebPage => {
+                context.current.web_page = Some(response.validators(&request));
+                let web_url = response.url.clone();
+                let text = response.into_text(&request)?;
+                context.resolve_web_page(web_url, &text)
+            }
+            State::Header(candidates) => {
+                let validators = response.validators(&request);
+                let header_json_url = response.url.clone();
+                let result = response
+                    .into_text(&request)
+                    .and_then(|text| parse_header(request.url.clone(), text));
+                match result {
+                    Ok((header, raw, encoding)) => {
+                        context.current.header = Some(validators);
+                        context.found_header(FoundHeader {
+                            url: header_json_url,
+                            header,
+                            raw,
+                            encoding,
+                            attempts: candidates.attempts,
+                        })
+                    }
+                    Err(error) => candidates.reject(*context, request.url, error),
+                }
+            }
+            State::PreviousHeader => {
+                let validators = response.validators(&request);
+                let header_json_url = response.url.clone();
+                let result = response
+                    .into_text(&request)
+                    .and_then(|text| parse_header(request.url.clone(), text));
+                match result {
+                    Ok((header, raw, encoding)) => {
+                        context.current.header = Some(validators);
+                        context.found_header(FoundHeader {
+                            url: header_json_url,
+                            header,
+                            raw,
+                            encoding,
+                            attempts: Vec::new(),
+                        })
+                    }
+                    // The page may now point somewhere else: resolve it from scratch.
+                    Err(_) => Ok(context.restart()),
+                }
+            }
+            State::Data(header) => {
+                context.current.data = Some(response.validators(&request));
+                let data = parse_data(request.url.clone(), response.into_text(&request)?)?;
+                Ok(match header {
+                    Some(header) => context.finish(*header, data),
+                    None => context.refetch_header(data),
+                })
+            }
+            State::HeaderForData(data) => {
+                let validators = response.validators(&request);
+                let header_json_url = response.url.clone();
+                let (header, raw, encoding) =
+                    parse_header(request.url.clone(), response.into_text(&request)?)?;
+                context.current.header = Some(validators);
+                let header = FoundHeader {
+                    url: header_json_url,
+                    header,
+                    raw,
+                    encoding,
+                    attempts: Vec::new(),
+                };
+                if join_url(&header.url, &header.header.data_url)? == data.url {
+                    Ok(context.finish(header, data))
+                } else {
+                    context.found_header(header)
+                }
+            }
+        }
+    }
+
+    /// Report that [`TableResolver::request`] could not be fetched at all, e.g. an [`Error::Network`].
+    ///
+    /// A failed header candidate is recorded and the next candidate is requested; any other failure ends the
+    /// resolution.
+    ///
+    /// # Errors
+    ///
+    /// Returns `error` itself, or [`Error::HeaderCandidates`] when it was the last header candidate.
+    pub fn fail(self, error: Error) -> Result<ResolveStep> {
+        match *self.state {
+            State::Header(candidates) => candidates.reject(*self.context, self.request.url, error),
+            State::WebPage | State::PreviousHeader | State::Data(_) | State::HeaderForData(_) => {
+                Err(error)
+            }
+        }
+    }
+}
+
+impl Context {
+    /// Request `url`, conditionally when the previous fetch recorded validators for it.
+    fn fetch(self, state: State, url: Url, stage: FetchStage) -> TableResolver {
+        let mut request = HttpRequest::new(url, stage);
+        if let Some(validators) = self
+            .previous
+            .get(stage)
+            .filter(|validators| validators.url == request.url)
+        {
+            request.if_none_match.clone_from(&validators.etag);
+            request
+                .if_modified_since
+                .clone_from(&validators.last_modified);
+        }
+        TableResolver {
+            request,
+            state: Box::new(state),
+            context: Box::new(self),
+        }
+    }
+
+    /// Request `url` without validators, because its content is needed.
+    fn refetch(mut self, state: State, url: Url, stage: FetchStage) -> TableResolver {
+        self.previous = TableValidators::default();
+        self.fetch(state, url, stage)
+    }
+
+    /// Resolve the table from scratch, without validators.
+    fn restart(mut self) -> ResolveStep {
+        self.current = TableValidators::default();
+        let web_url = self.web_url.clone();
+        ResolveStep::Fetch(self.refetch(State::WebPage, web_url, FetchStage::WebPage))
+    }
+
+    /// Handle a `304 Not Modified` response to the request of `state`.
+    fn not_modified(
+        mut self,
+        state: State,
+        request: &HttpRequest,
+        response: &HttpResponse,
+    ) -> ResolveStep {
+        let stage = request.stage;
+        let validators = self
+            .previous
+            .get(stage)
+            .cloned()
+            .map(|previous| Validators {
+                etag: response.etag.clone().or(previous.etag),
+                last_modified: response.last_modified.clone().or(previous.last_modified),
+                url: previous.url,
+            });
+        match state {
+            State::WebPage => {
+                self.current.web_page = validators;
+                match self.previous.header.clone() {
+                    // The header is defined in the unmodified page.
+                    Some(header) if header.url == request.url => {
+                        self.current.header = Some(header);
+                        self.header_unchanged()
+                    }
+                    Some(header) => ResolveStep::Fetch(self.fetch(
+                        State::PreviousHeader,
+                        header.url,
+                        FetchStage::Header,
+                    )),
+                    None => self.restart(),
+                }
+            }
+            State::Header(_) | State::PreviousHeader => {
+                self.current.header = validators;
+                self.header_unchanged()
+            }
+            State::Data(None) => {
+                self.current.data = validators;
+                ResolveStep::NotModified
+            }
+            State::Data(header @ Some(_)) => ResolveStep::Fetch(self.refetch(
+                State::Data(header),
+                request.url.clone(),
+                FetchStage::Data,
+            )),
+            // Only unconditional requests are made in this state.
+            State::HeaderForData(_) => self.restart(),
+        }
+    }
+
+    /// Continue with the data of the previous fetch, whose header did not change.
+    fn header_unchanged(mut self) -> ResolveStep {
+        let header_url = self
+            .current
+            .header
+            .as_ref()
+            .map(|header| header.url.clone());
+        match self.previous.data.clone() {
+            // The data is defined in the unmodified header document.
+            Some(data) if Some(&data.url) == header_url.as_ref() => {
+                self.current.data = Some(data);
+                ResolveStep::NotModified
+            }
+            Some(data) => {
+                ResolveStep::Fetch(self.fetch(State::Data(None), data.url, FetchStage::Data))
+            }
+            None => self.restart(),
+        }
+    }
+
+    /// Read the unmodified header again, because the data changed.
+    fn refetch_header(self, data: FetchedData) -> ResolveStep {
+        let page_url = self.current.web_page.as_ref().map(|page| &page.url);
+        match self.current.header.clone() {
+            // The header is defined in the web page, which has to be resolved again.
+            Some(header) if Some(&header.url) == page_url => self.restart(),
+            Some(header) => ResolveStep::Fetch(self.refetch(
+                State::HeaderForData(data),
+                header.url,
+                FetchStage::Header,
+            )),
+            None => self.restart(),
+        }
+    }
+
+    /// Read the header (or its candidates) from the web page.
+    fn resolve_web_page(mut self, web_url: Url, page: &DecodedText) -> Result<ResolveStep> {
+        let (query, used_text) = header_query_with_fallback::<BmsTableHeader>(&page.text)
+            .map_err(|e| e.at_url(&web_url))?;
+        match query {
+            HeaderQueryContent::Url(_) => {
+                let candidates = extract_header_candidates(&used_text).into();
+                next_candidate(self, web_url, candidates, Vec::new(), Vec::new())
+            }
+            HeaderQueryContent::Value(header) => {
+                self.current.header.clone_from(&self.current.web_page);
+                self.found_header(FoundHeader {
+                    url: web_url,
+                    header,
+                    raw: used_text,
+                    encoding: page.encoding,
+                    attempts: Vec::new(),
+                })
+            }
+            HeaderQueryContent::Inline(InlineTable {
+                header,
+                header_raw,
+                data,
+                data_raw,
+            }) => {
+                self.current.header.clone_from(&self.current.web_page);
+                let header = FoundHeader {
+                    url: web_url,
+                    header,
+                    raw: header_raw,
+                    encoding: page.encoding,
+                    attempts: Vec::new(),
+                };
+                match data.zip(data_raw) {
+                    Some((data, raw)) => {
+                        self.current.data.clone_from(&self.current.web_page);
+                        let data = FetchedData {
+                            url: header.url.clone(),
+                            data,
+                            raw,
+                            encoding: page.encoding,
+                        };
+                        Ok(self.finish(header, data))
+                    }
+                    None => self.found_header(header),
+                }
+            }
+        }
+    }
+
+    /// Request the chart data referenced by a found header.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ResolveUrl`] when `data_url` cannot be resolved against the header URL.
+    fn found_header(self, header: FoundHeader) -> Result<ResolveStep> {
+        let data_json_url = join_url(&header.url, &header.header.data_url)?;
+        Ok(ResolveStep::Fetch(self.fetch(
+            State::Data(Some(Box::new(header))),
+            data_json_url,
+            FetchStage::Data,
+        )))
+    }
+
+    /// Assemble the resolved table.
+    fn finish(self, header: FoundHeader, data: FetchedData) -> ResolveStep {
+        ResolveStep::Done(Box::new(FetchedTable {
+            table: BmsTable {
+                header: header.header,
+                data: data.data,
+            },
+            raw: BmsTableRaw {
+                header_json_url: header.url,
+                header_raw: header.raw,
+                header_encoding: header.encoding.to_string(),
+                data_json_url: data.url,
+                data_raw: data.raw,
+                data_encoding: data.encoding.to_string(),
+            },
+            header_attempts: header.attempts,
+            validators: self.current,
+        }))
+    }
+}
+
+impl Candidates {
+    /// Record the failure of the current candidate and move on to the next one.
+    fn reject(mut self, context: Context, url: Url, error: Error) -> Result<ResolveStep> {
+        self.attempts.push(HeaderAttempt {
+            candidate: self.current,
+            url: Some(url),
+            error,
+        });
+        next_candidate(
+            context,
+            self.web_url,
+            self.remaining,
+            self.tried,
+            self.attempts,
+        )
+    }
+}
+
+/// Request the next header candidate not tried yet.
+///
+/// Candidates resolving to an already tried URL are skipped.
+///
+/// # Errors
+///
+/// Returns the error of the only candidate when there was one, and [`Error::HeaderCandidates`] when several
+/// candidates were tried and all of them failed.
+fn next_candidate(
+    context: Context,
+    web_url: Url,
+    mut remaining: VecDeque<HeaderCandidate>,
+    mut tried: Vec<Url>,
+    mut attempts: Vec<HeaderAttempt>,
+) -> Result<ResolveStep> {
+    while let Some(candidate) = remaining.pop_front() {
+        let header_json_url = match join_url(&web_url, &candidate.url) {
+            Ok(url) => url,
+            Err(error) => {
+                attempts.push(HeaderAttempt {
+                    candidate,
+                    url: None,
+                    error,
+                });
+                continue;
+            }
+        };
+        if tried.contains(&header_json_url) {
+            continue;
+        }
+        tried.push(header_json_url.clone());
+        let state = State::Header(Candidates {
+            web_url,
+            current: candidate,
+            remaining,
+            tried,
+            attempts,
+        });
+        return Ok(ResolveStep::Fetch(context.fetch(
+            state,
+            header_json_url,
+            FetchStage::Header,
+        )));
+    }
+    if attempts.len() == 1
+        && let Some(attempt) = attempts.pop()
+    {
+        return Err(attempt.error);
+    }
+    Err(Error::HeaderCandidates {
+        url: web_url,
+        attempts,
+    })
+}
+
+/// Parse a fetched header candidate, returning the header, the raw JSON used and its encoding.
+///
+/// # Errors
+///
+/// Returns [`Error::CycledHeader`] when the URL serves another table page, and [`Error::HeaderJson`] when it
+/// is not a valid header.
+fn parse_header(
+    header_json_url: Url,
+    text: DecodedText,
+) -> Result<(BmsTableHeader, String, &'static str)> {
+    match json_with_fallback::<BmsTableHeader>(&text.text) {
+        Ok((header, raw)) => Ok((header, raw, text.encoding)),
+        Err(_) if try_extract_bmstable_from_html(&text.text).is_ok() => Err(Error::CycledHeader {
+            url: header_json_url,
+        }),
+        Err(source) => Err(Error::HeaderJson {
+            url: Some(header_json_url),
+            source,
+        }),
+    }
+}
+
+/// Parse fetched chart data.
+///
+/// # Errors
+///
+/// Returns [`Error::DataJson`] when the text is not valid chart data.
+fn parse_data(data_json_url: Url, text: DecodedText) -> Result<FetchedData> {
+    let (data, raw) =
+        json_with_fallback::<BmsTableData>(&text.text).map_err(|source| Error::DataJson {
+            url: Some(data_json_url.clone()),
+            source,
+        })?;
+    Ok(FetchedData {
+        url: data_json_url,
+        data,
+        raw,
+        encoding: text.encoding,
+    })
+}
+
+/// Resolve a reference found in a document against the document URL.
+///
+/// # Errors
+///
+/// Returns [`Error::ResolveUrl`] when the reference cannot be joined.
+fn join_url(base: &Url, reference: &str) -> Result<Url> {
+    base.join(reference).map_err(|source| Error::ResolveUrl {
+        base: base.clone(),
+        reference: reference.to_string(),
+        source,
+    })
+}
@@ -0,0 +1,369 @@
+//! Retries and per-host politeness limits
+//!
+//! [`RetryPolicy`] decides whether a failed request is sent again and how long to wait first: transient
+//! network errors and retryable statuses are retried with exponential backoff and jitter, and a `Retry-After`
+//! header from the server takes precedence over the computed delay.
+//!
+//! [`HostLimiter`] caps how many requests run against one host at the same time and spaces their starts by a
+//! minimum delay. A fetcher keeps it behind an [`Arc`](std::sync::Arc), so every clone of the fetcher shares
+//! the same limits.
+#![cfg(feature = "reqwest")]
+
+use std::{
+    collections::{BTreeSet, HashMap, hash_map::RandomState},
+    hash::{BuildHasher, Hasher},
+    pin::pin,
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use url::Url;
+
+use crate::{error::Result, fetch::HttpResponse};
+
+/// Retry policy of a fetcher.
+///
+/// The default policy makes up to 3 attempts, waiting 500 ms then 1 s (with jitter) in between, and retries
+/// network errors classified as transient and statuses 408, 429 and 5xx.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use bms_table::fetch::{HttpResponse, RetryPolicy};
+///
+/// let policy = RetryPolicy::new().max_attempts(5).jitter(false);
+/// let url = url::Url::parse("https://example.com/table.html").unwrap();
+/// let unavailable = HttpResponse::new(url, 503, Vec::new()).with_retry_after("2");
+/// assert_eq!(policy.retry_delay(1, &Ok(unavailable)), Some(Duration::from_secs(2)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, the first one included.
+    max_attempts: u32,
+    /// Delay before the second attempt, doubled for each following one.
+    initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts.
+    max_backoff: Duration,
+    /// Whether delays are randomized, so that clients failing together do not retry together.
+    jitter: bool,
+    /// Retried statuses, or `None` for 408, 429 and 5xx.
+    statuses: Option<BTreeSet<u16>>,
+    /// Whether a `Retry-After` header overrides the computed delay.
+    respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default policy: 3 attempts, with a backoff of 500 ms up to 30 s.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            statuses: None,
+            respect_retry_after: true,
+        }
+    }
+
+    /// Create a policy that never retries.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self::new().max_attempts(1)
+    }
+
+    /// Set the maximum number of attempts, the first one included; `0` and `1` both disable retries.
+    #[must_use]
+    pub const fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled for each following one up to `max`.
+    #[must_use]
+    pub const fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Randomize each delay between half and all of its value.
+    #[must_use]
+    pub const fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the HTTP statuses that are retried, replacing the default 408, 429 and 5xx.
+    #[must_use]
+    pub fn retry_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.statuses = Some(statuses.into_iter().collect());
+        self
+    }
+
+    /// Wait as long as a `Retry-After` header asks instead of the computed delay.
+    ///
+    /// A request asked to wait longer than the maximum backoff is not retried; an unparseable value is ignored.
+    #[must_use]
+    pub const fn respect_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    /// Delay before sending a request again after its `attempt`-th attempt (starting at 1) ended with `outcome`,
+    /// or `None` when the outcome is final.
+    #[must_use]
+    pub fn retry_delay(&self, attempt: u32, outcome: &Result<HttpResponse>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let retry_after = match outcome {
+            Ok(response) if self.is_retryable(response.status) => response.retry_after.as_deref(),
+            Err(error) if error.is_transient() => None,
+            _ => return None,
+        };
+        // Without a usable `Retry-After`, fall back to the computed delay.
+        retry_after
+            .filter(|_| self.respect_retry_after)
+            .and_then(|value| parse_retry_after(value, SystemTime::now()))
+            .map_or_else(
+                || Some(self.backoff_delay(attempt)),
+                |delay| (delay <= self.max_backoff).then_some(delay),
+            )
+    }
+
+    /// Whether responses with `status` are retried.
+    fn is_retryable(&self, status: u16) -> bool {
+        self.statuses.as_ref().map_or_else(
+            || matches!(status, 408 | 429 | 500..=599),
+            |statuses| statuses.contains(&status),
+        )
+    }
+
+    /// Exponential backoff after the `attempt`-th attempt, with jitter if enabled.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        if self.jitter {
+            // Any random source does: the std hasher keys are randomly seeded.
+            let random = RandomState::new().build_hasher().finish();
+            let half = delay / 2;
+            half + half.mul_f64(random as f64 / u64::MAX as f64)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Per-host concurrency and rate limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HostLimits {
+    /// Maximum number of requests running against one host at the same time.
+    pub(crate) max_concurrent: Option<usize>,
+    /// Minimum delay between the starts of two requests to one host.
+    pub(crate) min_delay: Duration,
+}
+
+impl HostLimits {
+    /// Limits that let every request through immediately.
+    pub(crate) const UNLIMITED: Self = Self {
+        max_concurrent: None,
+        min_delay: Duration::ZERO,
+    };
+}
+
+/// Requests in flight and next allowed start of one host.
+#[derive(Debug)]
+struct HostSlots {
+    /// Requests holding a permit.
+    active: usize,
+    /// Earliest start of the next request.
+    next_start: Instant,
+}
+
+/// Enforces [`HostLimits`] across all requests of a fetcher and its clones.
+#[derive(Debug)]
+pub(crate) struct HostLimiter {
+    /// Configured limits.
+    limits: HostLimits,
+    /// State of every host requested so far.
+    hosts: Mutex<HashMap<String, HostSlots>>,
+    /// Wakes blocking waiters when a permit is released.
+    released: Condvar,
+    /// Wakes async waiters when a permit is released.
+    released_async: tokio::sync::Notify,
+}
+
+/// Permission to send one request, released when dropped.
+#[derive(Debug)]
+pub(crate) struct HostPermit<'a> {
+    /// Limiter the permit belongs to.
+    limiter: &'a HostLimiter,
+    /// Host the permit counts against, or `None` when the limiter is unlimited.
+    host: Option<String>,
+}
+
+impl HostLimiter {
+    /// Create a limiter enforcing `limits`.
+    pub(crate) fn new(limits: HostLimits) -> Self {
+        Self {
+            limits,
+            hosts: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+            released_async: tokio::sync::Notify::new(),
+        }
+    }
+
+    /// Wait until a request to `url` may start.
+    pub(crate) async fn acquire(&self, url: &Url) -> HostPermit<'_> {
+        let Some(host) = self.host_key(url) else {
+            return self.permit(None);
+        };
+        loop {
+            // Register for the wake-up before checking, so that a release in between is not missed.
+            let mut released = pin!(self.released_async.notified());
+            released.as_mut().enable();
+            let reserved = self.reserve(&mut self.lock(), &host);
+            if let Some(start) = reserved {
+                // Created before waiting, so that the slot is released if the request is dropped meanwhile.
+                let permit = self.permit(Some(host));
+                tokio::time::sleep_until(start.into()).await;
+                return permit;
+            }
+            released.await;
+        }
+    }
+
+    /// Block the current thread until a request to `url` may start.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn acquire_blocking(&self, url: &Url) -> HostPermit<'_> {
+        let Some(host) = self.host_key(url) else {
+            return self.permit(None);
+        };
+        let mut hosts = self.lock();
+        loop {
+            if let Some(start) = self.reserve(&mut hosts, &host) {
+                drop(hosts);
+                let permit = self.permit(Some(host));
+                std::thread::sleep(start.saturating_duration_since(Instant::now()));
+                return permit;
+            }
+            hosts = self
+                .released
+                .wait(hosts)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Host `url` counts against, or `None` when no limit applies.
+    fn host_key(&self, url: &Url) -> Option<String> {
+        if self.limits == HostLimits::UNLIMITED {
+            return None;
+        }
+        Some(url.host_str().unwrap_or_default().to_string())
+    }
+
+    /// Take a slot of `host` if one is free, and return when the request may start.
+    fn reserve(&self, hosts: &mut HashMap<String, HostSlots>, host: &str) -> Option<Instant> {
+        let now = Instant::now();
+        let slots = hosts.entry(host.to_string()).or_insert(HostSlots {
+            active: 0,
+            next_start: now,
+        });
+        if self
+            .limits
+            .max_concurrent
+            .is_some_and(|max| slots.active >= max.max(1))
+        {
+            return None;
+        }
+        slots.active += 1;
+        let start = slots.next_start.max(now);
+        slots.next_start = start + self.limits.min_delay;
+        Some(start)
+    }
+
+    /// Lock the host states; a panic while holding the lock cannot leave them inconsistent.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, HostSlots>> {
+        self.hosts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Permit counting against `host`.
+    const fn permit(&self, host: Option<String>) -> HostPermit<'_> {
+        HostPermit {
+            limiter: self,
+            host,
+        }
+    }
+}
+
+impl Drop for HostPermit<'_> {
+    fn drop(&mut self) {
+        let Some(host) = &self.host else {
+            return;
+        };
+        if let Some(slots) = self.limiter.lock().get_mut(host) {
+            slots.active -= 1;
+        }
+        self.limiter.released.notify_all();
+        self.limiter.released_async.notify_waiters();
+    }
+}
+
+/// Parse a `Retry-After` value, either a number of seconds or an HTTP date, into a delay from `now`.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = parse_http_date(value)?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Parse an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let [_, day, month, year, time, "GMT"] = value.split_whitespace().collect::<Vec<_>>()[..]
+    else {
+        return None;
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = year.parse().ok()?;
+    let mut hms = time.split(':').map(str::parse::<u64>);
+    let (Some(Ok(h)), Some(Ok(m)), Some(Ok(s)), None) =
+        (hms.next(), hms.next(), hms.next(), hms.next())
+    else {
+        return None;
+    };
+    // IMF-fixdates have four-digit years; the bound also keeps the arithmetic below from overflowing.
+    if !(1970..=9999).contains(&year) || !(1..=31).contains(&day) || h > 23 || m > 59 || s > 60 {
+        return None;
+    }
+    // Days since the epoch of a proleptic Gregorian date (Howard Hinnant's `days_from_civil`).
+    let (y, mp) = if month > 2 {
+        (year, month - 3)
+    } else {
+        (year - 1, month + 9)
+    };
+    let era = y / 400;
+    let yoe = y % 400;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+    let seconds = days * 86_400 + h * 3_600 + m * 60 + s;
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+}
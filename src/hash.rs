@@ -0,0 +1,187 @@
+//! Validated chart hashes
+//!
+//! Charts are identified by the MD5 or SHA-256 hash of their BMS file, written in tables as hex strings.
+//! [`Md5`] and [`Sha256`] check the number of hex digits, accept surrounding whitespace and uppercase
+//! digits, and store the raw bytes, so they are `Copy` and cheap to hash and compare. They display and
+//! serialize as lowercase hex.
+//!
+//! Invalid hashes never fail a document: deserializing a [`ChartItem`](crate::ChartItem) reads an empty or
+//! invalid `md5`/`sha256` as `None` and skips invalid entries of the course hash lists, while lenient parsing
+//! also reports them.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::{Md5, hash::ParseHashError};
+//!
+//! let md5: Md5 = " 0123456789ABCDEF0123456789abcdef\n".parse().unwrap();
+//! assert_eq!(md5.to_string(), "0123456789abcdef0123456789abcdef");
+//! assert_eq!(
+//!     "abc".parse::<Md5>(),
+//!     Err(ParseHashError::Length { expected: 32, found: 3 })
+//! );
+//! ```
+
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Error returned when a string is not a valid hex hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ParseHashError {
+    /// The trimmed string does not have the expected number of hex digits.
+    #[error("expected {expected} hex digits, found {found} characters")]
+    Length {
+        /// Expected number of hex digits.
+        expected: usize,
+        /// Number of characters found after trimming.
+        found: usize,
+    },
+    /// The string contains a character that is not a hex digit.
+    #[error("invalid hex digit {found:?} at position {index}")]
+    Digit {
+        /// Position of the character within the trimmed string.
+        index: usize,
+        /// The offending character.
+        found: char,
+    },
+}
+
+/// Decode `s` (trimmed, case-insensitive) into exactly `N` bytes.
+fn decode_hex<const N: usize>(s: &str) -> Result<[u8; N], ParseHashError> {
+    let s = s.trim();
+    let found = s.chars().count();
+    if found != N * 2 {
+        return Err(ParseHashError::Length {
+            expected: N * 2,
+            found,
+        });
+    }
+    let mut bytes = [0u8; N];
+    let mut digits = s.chars().enumerate().map(|(index, c)| {
+        c.to_digit(16)
+            .and_then(|d| u8::try_from(d).ok())
+            .ok_or(ParseHashError::Digit { index, found: c })
+    });
+    for byte in &mut bytes {
+        let (Some(hi), Some(lo)) = (digits.next(), digits.next()) else {
+            break;
+        };
+        *byte = (hi? << 4) | lo?;
+    }
+    Ok(bytes)
+}
+
+/// Write `bytes` as lowercase hex.
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
+
+/// MD5 hash of a BMS file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Md5([u8; 16]);
+
+impl Md5 {
+    /// Create a hash from its raw bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Raw bytes of the hash.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl FromStr for Md5 {
+    type Err = ParseHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_hex(s).map(Self)
+    }
+}
+
+impl fmt::Display for Md5 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl fmt::Debug for Md5 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Md5({self})")
+    }
+}
+
+/// SHA-256 hash of a BMS file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Sha256([u8; 32]);
+
+impl Sha256 {
+    /// Create a hash from its raw bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Raw bytes of the hash.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl FromStr for Sha256 {
+    type Err = ParseHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_hex(s).map(Self)
+    }
+}
+
+impl fmt::Display for Sha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl fmt::Debug for Sha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sha256({self})")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Md5 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Md5 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Sha256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Sha256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,341 @@
+//! Typed values of well-known header fields
+//!
+//! Headers may declare the play mode of their charts in a `mode` field using beatoraja's mode names,
+//! e.g. `beat-7k` for 7-key SP, `beat-14k` for 14-key DP or `popn-9k` for PMS. [`KeyMode`] parses the
+//! known names and keeps any other value in [`KeyMode::Unknown`], serializing back to the original string.
+//!
+//! Courses list their play constraints as strings such as `grade_mirror`, `gauge_lr2` or `ln`.
+//! [`CourseConstraint`] covers the constraints understood by beatoraja and LR2 in the same way. Each
+//! constraint belongs to a [`ConstraintCategory`]; a course may use at most one constraint per category,
+//! which [`CourseInfo::constraint_conflicts`] checks.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::{CourseConstraint, KeyMode, header::ConstraintCategory};
+//!
+//! let mode: KeyMode = "beat-14k".into();
+//! assert_eq!(mode, KeyMode::Beat14K);
+//! assert!(mode.is_double());
+//! assert_eq!(KeyMode::from("keyboard-48k").as_str(), "keyboard-48k");
+//!
+//! let constraint: CourseConstraint = "gauge_lr2".into();
+//! assert_eq!(constraint.category(), ConstraintCategory::Gauge);
+//! assert_eq!(CourseConstraint::from("no_mine").as_str(), "no_mine");
+//! ```
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::CourseInfo;
+
+/// Play mode declared by a table header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyMode {
+    /// 5 keys + scratch, single play (`beat-5k`).
+    Beat5K,
+    /// 7 keys + scratch, single play (`beat-7k`).
+    Beat7K,
+    /// 10 keys + 2 scratches, double play (`beat-10k`).
+    Beat10K,
+    /// 14 keys + 2 scratches, double play (`beat-14k`).
+    Beat14K,
+    /// Pop'n 5 buttons (`popn-5k`).
+    Popn5K,
+    /// Pop'n 9 buttons, i.e. PMS (`popn-9k`).
+    Popn9K,
+    /// 24-key keyboard (`keyboard-24k`).
+    Keyboard24K,
+    /// 24-key keyboard, double (`keyboard-24k-double`).
+    Keyboard24KDouble,
+    /// Any other mode name, kept verbatim.
+    Unknown(String),
+}
+
+impl KeyMode {
+    /// Mode name as written in headers, e.g. `beat-7k`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Beat5K => "beat-5k",
+            Self::Beat7K => "beat-7k",
+            Self::Beat10K => "beat-10k",
+            Self::Beat14K => "beat-14k",
+            Self::Popn5K => "popn-5k",
+            Self::Popn9K => "popn-9k",
+            Self::Keyboard24K => "keyboard-24k",
+            Self::Keyboard24KDouble => "keyboard-24k-double",
+            Self::Unknown(s) => s,
+        }
+    }
+
+    /// Whether the mode is played on two sides (DP).
+    #[must_use]
+    pub const fn is_double(&self) -> bool {
+        matches!(
+            self,
+            Self::Beat10K | Self::Beat14K | Self::Keyboard24KDouble
+        )
+    }
+
+    /// Whether the mode is a pop'n style mode (PMS).
+    #[must_use]
+    pub const fn is_popn(&self) -> bool {
+        matches!(self, Self::Popn5K | Self::Popn9K)
+    }
+
+    /// Number of keys (excluding scratches) of a known mode.
+    #[must_use]
+    pub const fn key_count(&self) -> Option<u8> {
+        match self {
+            Self::Beat5K | Self::Popn5K => Some(5),
+            Self::Beat7K => Some(7),
+            Self::Popn9K => Some(9),
+            Self::Beat10K => Some(10),
+            Self::Beat14K => Some(14),
+            Self::Keyboard24K => Some(24),
+            Self::Keyboard24KDouble => Some(48),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<&str> for KeyMode {
+    fn from(s: &str) -> Self {
+        match s {
+            "beat-5k" => Self::Beat5K,
+            "beat-7k" => Self::Beat7K,
+            "beat-10k" => Self::Beat10K,
+            "beat-14k" => Self::Beat14K,
+            "popn-5k" => Self::Popn5K,
+            "popn-9k" => Self::Popn9K,
+            "keyboard-24k" => Self::Keyboard24K,
+            "keyboard-24k-double" => Self::Keyboard24KDouble,
+            _ => Self::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for KeyMode {
+    fn from(s: String) -> Self {
+        match Self::from(s.as_str()) {
+            Self::Unknown(_) => Self::Unknown(s),
+            known => known,
+        }
+    }
+}
+
+impl fmt::Display for KeyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for KeyMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for KeyMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Category of a course constraint; a course may use at most one constraint per category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ConstraintCategory {
+    /// Grade (dan) course and its lane option: `grade`, `grade_mirror`, `grade_random`.
+    Grade,
+    /// Hi-speed restriction: `no_speed`.
+    HiSpeed,
+    /// Judge restriction: `no_good`, `no_great`.
+    Judge,
+    /// Gauge type: `gauge_lr2`, `gauge_5k`, `gauge_7k`, `gauge_9k`, `gauge_24k`.
+    Gauge,
+    /// Long-note mode: `ln`, `cn`, `hcn`.
+    LongNote,
+    /// Unrecognized constraint.
+    Unknown,
+}
+
+/// A play constraint of a course.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CourseConstraint {
+    /// Grade course, lane options not allowed (`grade`).
+    Grade,
+    /// Grade course, mirror allowed (`grade_mirror`).
+    GradeMirror,
+    /// Grade course, random allowed (`grade_random`).
+    GradeRandom,
+    /// Hi-speed changes not allowed (`no_speed`).
+    NoSpeed,
+    /// GOOD judgements count as BAD (`no_good`).
+    NoGood,
+    /// GREAT and GOOD judgements count as BAD (`no_great`).
+    NoGreat,
+    /// LR2 style gauge (`gauge_lr2`).
+    GaugeLr2,
+    /// 5-key style gauge (`gauge_5k`).
+    Gauge5Keys,
+    /// 7-key style gauge (`gauge_7k`).
+    Gauge7Keys,
+    /// 9-key (pop'n) style gauge (`gauge_9k`).
+    Gauge9Keys,
+    /// 24-key style gauge (`gauge_24k`).
+    Gauge24Keys,
+    /// Long notes played as LN (`ln`).
+    Ln,
+    /// Long notes played as CN (`cn`).
+    Cn,
+    /// Long notes played as HCN (`hcn`).
+    Hcn,
+    /// Any other constraint, kept verbatim.
+    Unknown(String),
+}
+
+impl CourseConstraint {
+    /// Constraint name as written in headers, e.g. `grade_mirror`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Grade => "grade",
+            Self::GradeMirror => "grade_mirror",
+            Self::GradeRandom => "grade_random",
+            Self::NoSpeed => "no_speed",
+            Self::NoGood => "no_good",
+            Self::NoGreat => "no_great",
+            Self::GaugeLr2 => "gauge_lr2",
+            Self::Gauge5Keys => "gauge_5k",
+            Self::Gauge7Keys => "gauge_7k",
+            Self::Gauge9Keys => "gauge_9k",
+            Self::Gauge24Keys => "gauge_24k",
+            Self::Ln => "ln",
+            Self::Cn => "cn",
+            Self::Hcn => "hcn",
+            Self::Unknown(s) => s,
+        }
+    }
+
+    /// Category the constraint belongs to.
+    #[must_use]
+    pub const fn category(&self) -> ConstraintCategory {
+        match self {
+            Self::Grade | Self::GradeMirror | Self::GradeRandom => ConstraintCategory::Grade,
+            Self::NoSpeed => ConstraintCategory::HiSpeed,
+            Self::NoGood | Self::NoGreat => ConstraintCategory::Judge,
+            Self::GaugeLr2
+            | Self::Gauge5Keys
+            | Self::Gauge7Keys
+            | Self::Gauge9Keys
+            | Self::Gauge24Keys => ConstraintCategory::Gauge,
+            Self::Ln | Self::Cn | Self::Hcn => ConstraintCategory::LongNote,
+            Self::Unknown(_) => ConstraintCategory::Unknown,
+        }
+    }
+}
+
+impl From<&str> for CourseConstraint {
+    fn from(s: &str) -> Self {
+        match s {
+            "grade" => Self::Grade,
+            "grade_mirror" => Self::GradeMirror,
+            "grade_random" => Self::GradeRandom,
+            "no_speed" => Self::NoSpeed,
+            "no_good" => Self::NoGood,
+            "no_great" => Self::NoGreat,
+            "gauge_lr2" => Self::GaugeLr2,
+            "gauge_5k" => Self::Gauge5Keys,
+            "gauge_7k" => Self::Gauge7Keys,
+            "gauge_9k" => Self::Gauge9Keys,
+            "gauge_24k" => Self::Gauge24Keys,
+            "ln" => Self::Ln,
+            "cn" => Self::Cn,
+            "hcn" => Self::Hcn,
+            _ => Self::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for CourseConstraint {
+    fn from(s: String) -> Self {
+        match Self::from(s.as_str()) {
+            Self::Unknown(_) => Self::Unknown(s),
+            known => known,
+        }
+    }
+}
+
+impl fmt::Display for CourseConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for CourseConstraint {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CourseConstraint {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for CourseConstraint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CourseConstraint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Contradictory constraints of one course: several different constraints of the same category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintConflict {
+    /// Category used more than once.
+    pub category: ConstraintCategory,
+    /// The distinct constraints of that category, in declaration order.
+    pub constraints: Vec<CourseConstraint>,
+}
+
+impl CourseInfo {
+    /// Report contradictory constraint combinations, e.g. two different gauge constraints.
+    ///
+    /// Repeating the same constraint is not a conflict, and unknown constraints are never reported.
+    #[must_use]
+    pub fn constraint_conflicts(&self) -> Vec<ConstraintConflict> {
+        let mut by_category: Vec<ConstraintConflict> = Vec::new();
+        for constraint in &self.constraint {
+            let category = constraint.category();
+            if category == ConstraintCategory::Unknown {
+                continue;
+            }
+            match by_category.iter_mut().find(|c| c.category == category) {
+                Some(entry) if !entry.constraints.contains(constraint) => {
+                    entry.constraints.push(constraint.clone());
+                }
+                Some(_) => {}
+                None => by_category.push(ConstraintConflict {
+                    category,
+                    constraints: vec![constraint.clone()],
+                }),
+            }
+        }
+        by_category.retain(|c| c.constraints.len() > 1);
+        by_category
+    }
+}
@@ -0,0 +1,203 @@
+//! Difficulty level ordering
+//!
+//! Chart levels are plain strings such as `"0"`, `"12"`, `"!i"` or `"?"`, and their display order is
+//! defined per table by [`BmsTableHeader::level_order`]. [`Level`] pairs a level name with its rank in
+//! that order so levels of the same table can be compared, sorted and grouped consistently:
+//!
+//! - levels listed in `level_order` come first, in the listed order;
+//! - the remaining levels follow, numeric ones in numeric order, then the others by name.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(feature = "serde")]
+//! # fn main() -> anyhow::Result<()> {
+//! use std::cmp::Ordering;
+//! use bms_table::BmsTableHeader;
+//!
+//! let header: BmsTableHeader = serde_json::from_str(
+//!     r#"{ "name": "Test", "symbol": "t", "data_url": "charts.json", "level_order": [0, 1, 2, "!i"] }"#,
+//! )?;
+//! assert_eq!(header.compare_levels("2", "!i"), Ordering::Less);
+//! assert_eq!(header.compare_levels("!i", "10"), Ordering::Less);
+//! assert_eq!(header.compare_levels("10", "?"), Ordering::Less);
+//! assert!(header.level("1") < header.level("2"));
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "serde"))]
+//! # fn main() {}
+//! ```
+
+use std::{cmp::Ordering, collections::BTreeMap, collections::HashMap, fmt};
+
+use crate::{BmsTable, BmsTableHeader, ChartItem};
+
+/// A difficulty level of a table, ranked by the table's `level_order`.
+///
+/// Comparisons are only meaningful between levels created from the same header.
+#[derive(Debug, Clone, Copy)]
+pub struct Level<'a> {
+    /// Level name as written in the chart data.
+    name: &'a str,
+    /// Rank derived from the header's `level_order`.
+    rank: Rank,
+}
+
+/// Sort rank of a level within its table.
+#[derive(Debug, Clone, Copy)]
+enum Rank {
+    /// Listed in `level_order` at the given position.
+    Listed(usize),
+    /// Not listed, but parses as a finite number.
+    Numeric(f64),
+    /// Not listed and not numeric.
+    Other,
+}
+
+impl Rank {
+    /// Compare two ranks; listed levels sort first, then numeric ones, then the others.
+    fn cmp(self, other: Self) -> Ordering {
+        match (self, other) {
+            (Self::Listed(a), Self::Listed(b)) => a.cmp(&b),
+            (Self::Numeric(a), Self::Numeric(b)) => a.total_cmp(&b),
+            (Self::Other, Self::Other) => Ordering::Equal,
+            (Self::Listed(_), _) | (Self::Numeric(_), Self::Other) => Ordering::Less,
+            (_, Self::Listed(_)) | (Self::Other, Self::Numeric(_)) => Ordering::Greater,
+        }
+    }
+
+    /// Rank of a level that is not listed in `level_order`.
+    fn unlisted(name: &str) -> Self {
+        name.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map_or(Self::Other, Self::Numeric)
+    }
+}
+
+impl<'a> Level<'a> {
+    /// Level name as written in the chart data, e.g. `"12"` or `"!i"`.
+    #[must_use]
+    pub const fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Position of the level in the header's `level_order`, if listed.
+    #[must_use]
+    pub const fn position(&self) -> Option<usize> {
+        match self.rank {
+            Rank::Listed(i) => Some(i),
+            Rank::Numeric(_) | Rank::Other => None,
+        }
+    }
+
+    /// Numeric value of the level, if it parses as a finite number.
+    #[must_use]
+    pub fn as_number(&self) -> Option<f64> {
+        self.name
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+    }
+}
+
+impl PartialEq for Level<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Level<'_> {}
+
+impl PartialOrd for Level<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Level<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank
+            .cmp(other.rank)
+            .then_with(|| self.name.cmp(other.name))
+    }
+}
+
+impl fmt::Display for Level<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl BmsTableHeader {
+    /// Rank a level name according to this header's `level_order`.
+    #[must_use]
+    pub fn level<'a>(&self, name: &'a str) -> Level<'a> {
+        let rank = self
+            .level_order
+            .iter()
+            .position(|l| l == name)
+            .map_or_else(|| Rank::unlisted(name), Rank::Listed);
+        Level { name, rank }
+    }
+
+    /// Compare two level names of this table according to its `level_order`.
+    #[must_use]
+    pub fn compare_levels(&self, a: &str, b: &str) -> Ordering {
+        self.level(a).cmp(&self.level(b))
+    }
+
+    /// Build a lookup from level name to its first position in `level_order`.
+    fn level_positions(&self) -> HashMap<&str, usize> {
+        let mut positions = HashMap::with_capacity(self.level_order.len());
+        for (i, name) in self.level_order.iter().enumerate() {
+            positions.entry(name.as_str()).or_insert(i);
+        }
+        positions
+    }
+}
+
+impl BmsTable {
+    /// Distinct levels used by the charts, sorted by the header's `level_order`.
+    #[must_use]
+    pub fn levels(&self) -> Vec<Level<'_>> {
+        self.charts_by_level()
+            .into_iter()
+            .map(|(level, _)| level)
+            .collect()
+    }
+
+    /// Group the charts by level, with groups sorted by the header's `level_order`.
+    ///
+    /// Charts keep their original order within each group.
+    #[must_use]
+    pub fn charts_by_level(&self) -> Vec<(Level<'_>, Vec<&ChartItem>)> {
+        let positions = self.header.level_positions();
+        let mut groups: BTreeMap<Level<'_>, Vec<&ChartItem>> = BTreeMap::new();
+        for chart in &self.data.charts {
+            groups
+                .entry(ranked(&positions, &chart.level))
+                .or_default()
+                .push(chart);
+        }
+        groups.into_iter().collect()
+    }
+
+    /// Stable-sort the charts by level according to the header's `level_order`.
+    pub fn sort_charts_by_level(&mut self) {
+        let positions = self.header.level_positions();
+        self.data
+            .charts
+            .sort_by(|a, b| ranked(&positions, &a.level).cmp(&ranked(&positions, &b.level)));
+    }
+}
+
+/// Rank a level name using a precomputed `level_order` lookup.
+fn ranked<'a>(positions: &HashMap<&str, usize>, name: &'a str) -> Level<'a> {
+    let rank = positions
+        .get(name)
+        .map_or_else(|| Rank::unlisted(name), |&i| Rank::Listed(i));
+    Level { name, rank }
+}
@@ -5,18 +5,28 @@
 //!
 //! # Feature overview
 //!
-//! - Parse header JSON into [`BmsTableHeader`], preserving unrecognized fields in `extra` for forward compatibility;
+//! - Parse header JSON into [`BmsTableHeader`], exposing well-known fields such as `tag` and [`KeyMode`] and preserving unrecognized fields in `extra` for forward compatibility;
 //! - Parse chart data into [`BmsTableData`], supporting a plain array of [`ChartItem`] structure;
+//! - Parse large chart data into borrowed [`BmsTableDataRef`] views without allocating every string;
+//! - Load tables from any self-describing serde format besides JSON, e.g. YAML or `MessagePack`;
+//! - Group and sort charts by [`Level`], honoring the header's `level_order`;
+//! - Optionally parse chart data and course charts leniently, skipping malformed entries and reporting them as diagnostics;
+//! - Optionally parse headers and chart data losslessly, so that re-serializing them keeps the original representation;
+//! - Course constraints are typed as [`CourseConstraint`], with a check for contradictory combinations;
+//! - Evaluate course trophies from a play's [`JudgeCounts`], using beatoraja's miss rate and score rate;
+//! - Chart hashes are validated and normalized as [`Md5`] and [`Sha256`];
 //! - Courses automatically convert `md5`/`sha256` lists into chart items, filling missing `level` with "0";
-//! - Extract the header JSON URL from HTML `<meta name="bmstable">`;
+//! - Extract the header JSON URL from HTML `<meta name="bmstable">`, or list every candidate with the heuristic that found it;
 //! - One-stop network fetching APIs (web page → header JSON → chart data);
 //! - Support fetching a list of difficulty tables into [`BmsTableList`]. [An example source page](https://darksabun.club/table/tablelist.html).
+//! - Report failures through a typed `Error` that tells the failed stage, the URL involved and whether retrying may help.
 //!
 //! # Feature flags
 //!
 //! - `serde`: enable serialization/deserialization support for types (enabled by default).
 //! - `scraper`: enable HTML parsing and bmstable header URL extraction (enabled by default; implicitly enabled by `reqwest`).
 //! - `reqwest`: enable the network fetching implementation (enabled by default; requires the `tokio` runtime).
+//! - `preserve_order`: keep the original key order of objects in lossless round-trips (disabled by default).
 //!
 //! # Quick start (network fetching)
 //!
@@ -77,8 +87,24 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod borrowed;
 pub mod de;
+pub mod error;
 pub mod fetch;
+pub mod hash;
+pub mod header;
+pub mod level;
+pub mod lossless;
+pub mod score;
+
+#[cfg(feature = "serde")]
+pub use borrowed::{BmsTableDataRef, ChartItemRef};
+#[cfg(feature = "serde")]
+pub use error::Error;
+pub use hash::{Md5, Sha256};
+pub use header::{CourseConstraint, KeyMode};
+pub use level::Level;
+pub use score::JudgeCounts;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -88,7 +114,10 @@ use serde_json::Value;
 use std::collections::BTreeMap;
 
 #[cfg(feature = "serde")]
-use crate::de::{de_numstring, deserialize_course_groups, deserialize_level_order};
+use crate::de::{
+    de_numstring, de_opt_hash, de_opt_key_mode, de_opt_numstring, deserialize_course_groups,
+    deserialize_level_order,
+};
 
 /// Top-level BMS difficulty table data structure.
 ///
@@ -104,7 +133,8 @@ pub struct BmsTable {
 
 /// BMS header information.
 ///
-/// Strictly parses common fields and preserves unrecognized fields in `extra` for forward compatibility.
+/// Strictly parses common fields, exposes well-known optional fields (`tag`, `mode`, `last_update`, `update_url`,
+/// `tag_order`, `comment`) as typed values, and preserves unrecognized fields in `extra` for forward compatibility.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BmsTableHeader {
@@ -126,6 +156,66 @@ pub struct BmsTableHeader {
         serde(default, deserialize_with = "deserialize_level_order")
     )]
     pub level_order: Vec<String>,
+    /// Tag shown in front of levels by beatoraja, e.g. "sl"
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_numstring",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub tag: Option<String>,
+    /// Key mode of the charts, e.g. `beat-7k`, `beat-14k` or `popn-9k`; a non-string value is read as `None`
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_key_mode",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub mode: Option<KeyMode>,
+    /// Last update time as written in the header (format varies between tables)
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_numstring",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub last_update: Option<String>,
+    /// URL to check for table updates
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_numstring",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub update_url: Option<String>,
+    /// Sort hint for the table's tag column as written in the header, e.g. "1"
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_numstring",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub tag_order: Option<String>,
+    /// Free-form comment shown alongside the table
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "de_opt_numstring",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub comment: Option<String>,
     /// Extra data (unrecognized fields from header JSON)
     #[cfg(feature = "serde")]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -153,7 +243,7 @@ pub struct CourseInfo {
     pub name: String,
     /// Constraint list, e.g. ["`grade_mirror`", "`gauge_lr2`", "ln"]
     #[cfg_attr(feature = "serde", serde(default))]
-    pub constraint: Vec<String>,
+    pub constraint: Vec<CourseConstraint>,
     /// List of trophies, defining requirements for different ranks
     #[cfg_attr(feature = "serde", serde(default))]
     pub trophy: Vec<Trophy>,
@@ -171,10 +261,12 @@ pub struct ChartItem {
     /// Difficulty level, e.g. "0"
     #[cfg_attr(feature = "serde", serde(default, deserialize_with = "de_numstring"))]
     pub level: String,
-    /// MD5 hash of the file
-    pub md5: Option<String>,
-    /// SHA256 hash of the file
-    pub sha256: Option<String>,
+    /// MD5 hash of the file (an empty or invalid value is treated as missing)
+    #[cfg_attr(feature = "serde", serde(default, deserialize_with = "de_opt_hash"))]
+    pub md5: Option<Md5>,
+    /// SHA256 hash of the file (an empty or invalid value is treated as missing)
+    #[cfg_attr(feature = "serde", serde(default, deserialize_with = "de_opt_hash"))]
+    pub sha256: Option<Sha256>,
     /// Song title
     pub title: Option<String>,
     /// Song subtitle
@@ -210,16 +302,24 @@ pub struct Trophy {
 /// Complete set of original JSON strings.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BmsTableRaw {
-    /// Full URL of the header JSON
+    /// Full URL of the header JSON (the page URL when the header is defined inline)
     #[cfg(feature = "scraper")]
     pub header_json_url: url::Url,
     /// Raw header JSON string
     pub header_raw: String,
-    /// Full URL of the chart data JSON
+    /// Encoding the header was decoded with, e.g. `UTF-8` or `Shift_JIS`
+    #[cfg(feature = "scraper")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub header_encoding: String,
+    /// Full URL of the chart data JSON (the page URL when the data is defined inline)
     #[cfg(feature = "scraper")]
     pub data_json_url: url::Url,
     /// Raw chart data JSON string
     pub data_raw: String,
+    /// Encoding the chart data was decoded with
+    #[cfg(feature = "scraper")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub data_encoding: String,
 }
 
 /// BMS difficulty table list item.
@@ -0,0 +1,259 @@
+//! Lossless round-trip of header and chart data JSON
+//!
+//! Parsing normalizes a table: course `md5`/`sha256` lists become chart objects with `level: "0"`,
+//! numeric levels and `level_order` entries become strings, a flat `course` array becomes a list of groups,
+//! and missing optional fields are written back as `null`. [`Lossless`] parses a document as usual and also
+//! remembers where the normalized form differs from the original. Serializing it writes the original
+//! representation back for every part whose parsed value has not been changed since, and the normalized
+//! form for the parts that were edited.
+//!
+//! With the `preserve_order` feature, object keys are also written back in their original order.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use bms_table::BmsTableData;
+//!
+//! let json = r#"[{"level":12,"title":"Song"},{"level":"!i","md5":""}]"#;
+//! let mut data = BmsTableData::from_json_str_lossless(json)?;
+//! assert_eq!(data.value.charts[0].level, "12");
+//! assert_eq!(
+//!     serde_json::to_value(&data)?,
+//!     serde_json::from_str::<serde_json::Value>(json)?
+//! );
+//!
+//! // Edited parts are written in their normalized form.
+//! data.value.charts[1].level = "13".to_string();
+//! let value = serde_json::to_value(&data)?;
+//! assert_eq!(value[0]["level"], 12);
+//! assert_eq!(value[1]["level"], "13");
+//! # Ok(())
+//! # }
+//! ```
+#![cfg(feature = "serde")]
+
+use serde::{Serialize, Serializer, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+use crate::{BmsTableData, BmsTableHeader};
+
+/// A parsed value together with what is needed to serialize it back in its original representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lossless<T> {
+    /// Parsed value; changes made to it are reflected when serializing.
+    pub value: T,
+    /// Differences between the original document and the normalized serialization of `value`.
+    patch: Option<Patch>,
+}
+
+/// Difference between an original JSON value and its normalized serialization.
+#[derive(Debug, Clone, PartialEq)]
+enum Patch {
+    /// The node differs as a whole.
+    Replace {
+        /// Normalized serialization of the parsed node.
+        normalized: Value,
+        /// Original node, or `None` when the key was absent from the original object.
+        original: Option<Value>,
+    },
+    /// Some fields of an object differ, or its keys were in a different order.
+    Object {
+        /// Patches of the differing fields.
+        fields: Vec<(String, Patch)>,
+        /// Original key order, when it differs from the normalized one.
+        order: Option<Vec<String>>,
+    },
+    /// Some elements of an array differ.
+    Array(Vec<(usize, Patch)>),
+    /// A flat array was normalized into a single group, e.g. a flat `course` array.
+    Unwrap(Option<Box<Patch>>),
+}
+
+impl Patch {
+    /// Compute the patch turning `normalized` back into `original`, or `None` if they are identical.
+    fn diff(original: &Value, normalized: &Value) -> Option<Self> {
+        let replace = || {
+            Some(Self::Replace {
+                normalized: normalized.clone(),
+                original: Some(original.clone()),
+            })
+        };
+        match (original, normalized) {
+            (Value::Array(o), Value::Array(n))
+                if !matches!(o.first(), Some(Value::Array(_)))
+                    && matches!(n.as_slice(), [Value::Array(group)] if group.len() == o.len()) =>
+            {
+                let inner = n.first()?;
+                Some(Self::Unwrap(Self::diff(original, inner).map(Box::new)))
+            }
+            (Value::Object(o), Value::Object(n)) => {
+                // Fields only present in the original cannot be restored field by field.
+                if o.keys().any(|k| !n.contains_key(k)) {
+                    return replace();
+                }
+                let fields: Vec<(String, Self)> = n
+                    .iter()
+                    .filter_map(|(k, nv)| {
+                        let patch = match o.get(k) {
+                            Some(ov) => Self::diff(ov, nv)?,
+                            None => Self::Replace {
+                                normalized: nv.clone(),
+                                original: None,
+                            },
+                        };
+                        Some((k.clone(), patch))
+                    })
+                    .collect();
+                let order = (!o.keys().eq(n.keys().filter(|k| o.contains_key(*k))))
+                    .then(|| o.keys().cloned().collect());
+                (!fields.is_empty() || order.is_some()).then_some(Self::Object { fields, order })
+            }
+            (Value::Array(o), Value::Array(n)) if o.len() == n.len() => {
+                let elements: Vec<(usize, Self)> = o
+                    .iter()
+                    .zip(n)
+                    .enumerate()
+                    .filter_map(|(i, (ov, nv))| Self::diff(ov, nv).map(|p| (i, p)))
+                    .collect();
+                (!elements.is_empty()).then_some(Self::Array(elements))
+            }
+            _ if original == normalized => None,
+            _ => replace(),
+        }
+    }
+
+    /// Apply the patch to the normalized serialization of the current value.
+    ///
+    /// Parts are only restored when they still equal the recorded normalized form. Returns `false`
+    /// when the node should be removed from its parent object.
+    fn apply(&self, current: &mut Value) -> bool {
+        match self {
+            Self::Replace {
+                normalized,
+                original,
+            } => {
+                if current != normalized {
+                    return true;
+                }
+                original.as_ref().is_some_and(|original| {
+                    current.clone_from(original);
+                    true
+                })
+            }
+            Self::Object { fields, order } => {
+                let Value::Object(map) = current else {
+                    return true;
+                };
+                let removed: Vec<&String> = fields
+                    .iter()
+                    .filter(|(key, patch)| map.get_mut(key).is_some_and(|v| !patch.apply(v)))
+                    .map(|(key, _)| key)
+                    .collect();
+                // `retain` keeps the order of the remaining keys, unlike `remove`.
+                map.retain(|key, _| !removed.contains(&key));
+                if let Some(order) = order {
+                    reorder(map, order);
+                }
+                true
+            }
+            Self::Array(elements) => {
+                if let Value::Array(arr) = current {
+                    for (i, patch) in elements {
+                        if let Some(v) = arr.get_mut(*i) {
+                            patch.apply(v);
+                        }
+                    }
+                }
+                true
+            }
+            Self::Unwrap(inner) => {
+                if let Value::Array(arr) = current
+                    && let [group @ Value::Array(_)] = arr.as_mut_slice()
+                {
+                    *current = group.take();
+                    if let Some(inner) = inner {
+                        inner.apply(current);
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+/// Move the keys listed in `order` to the front of `map`, in that order; other keys keep their order.
+fn reorder(map: &mut Map<String, Value>, order: &[String]) {
+    let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+    entries.sort_by_key(|(key, _)| order.iter().position(|k| k == key).unwrap_or(order.len()));
+    map.extend(entries);
+}
+
+impl<T: DeserializeOwned + Serialize> Lossless<T> {
+    /// Parse a JSON value, remembering its original representation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `value` does not deserialize as `T`, or `T` fails to serialize.
+    pub fn from_json_value(value: Value) -> serde_json::Result<Self> {
+        let parsed = T::deserialize(&value)?;
+        let normalized = serde_json::to_value(&parsed)?;
+        Ok(Self {
+            value: parsed,
+            patch: Patch::diff(&value, &normalized),
+        })
+    }
+
+    /// Parse a JSON string, remembering its original representation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid JSON or does not deserialize as `T`.
+    pub fn from_json_str(s: &str) -> serde_json::Result<Self> {
+        Self::from_json_value(serde_json::from_str(s)?)
+    }
+}
+
+impl<T> Lossless<T> {
+    /// Discard the original representation and return the parsed value.
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize> Serialize for Lossless<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(&self.value).map_err(serde::ser::Error::custom)?;
+        if let Some(patch) = &self.patch {
+            patch.apply(&mut value);
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl BmsTableHeader {
+    /// Parse a header from a JSON string, remembering its original representation.
+    ///
+    /// See [`Lossless`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid JSON or not a valid header.
+    pub fn from_json_str_lossless(s: &str) -> serde_json::Result<Lossless<Self>> {
+        Lossless::from_json_str(s)
+    }
+}
+
+impl BmsTableData {
+    /// Parse chart data from a JSON string, remembering its original representation.
+    ///
+    /// See [`Lossless`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `s` is not valid JSON or not valid chart data.
+    pub fn from_json_str_lossless(s: &str) -> serde_json::Result<Lossless<Self>> {
+        Lossless::from_json_str(s)
+    }
+}
@@ -0,0 +1,113 @@
+//! Trophy and course clear evaluation
+//!
+//! Course trophies require a maximum miss rate and a minimum score rate. [`JudgeCounts`] holds the judge
+//! results of a play and computes both rates the way beatoraja does:
+//!
+//! - EX score is `PGREAT × 2 + GREAT`, and the score rate is `EX score / (notes × 2) × 100`;
+//! - misses (BP) are `BAD + POOR + empty POOR`, plus the notes that were never judged, and the miss rate
+//!   is `misses / notes × 100`.
+//!
+//! A trophy is achieved when the miss rate is at most its `missrate` and the score rate at least its
+//! `scorerate`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bms_table::{JudgeCounts, Trophy};
+//!
+//! let play = JudgeCounts {
+//!     pgreat: 700,
+//!     great: 200,
+//!     good: 60,
+//!     bad: 10,
+//!     poor: 20,
+//!     total_notes: 1000,
+//!     ..JudgeCounts::default()
+//! };
+//! assert_eq!(play.exscore(), 1600);
+//! assert_eq!(play.misses(), 40);
+//!
+//! let gold = Trophy { name: "goldmedal".into(), missrate: 5.0, scorerate: 80.0 };
+//! assert!(gold.is_achieved(&play));
+//! ```
+
+use crate::{CourseInfo, Trophy};
+
+/// Judge counts of a play, as shown on the result screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct JudgeCounts {
+    /// PGREAT count
+    pub pgreat: u32,
+    /// GREAT count
+    pub great: u32,
+    /// GOOD count
+    pub good: u32,
+    /// BAD count
+    pub bad: u32,
+    /// POOR count (missed notes)
+    pub poor: u32,
+    /// Empty POOR count (presses without a note)
+    pub empty_poor: u32,
+    /// Total notes of the chart or course
+    pub total_notes: u32,
+}
+
+impl JudgeCounts {
+    /// EX score, `PGREAT × 2 + GREAT`.
+    #[must_use]
+    pub const fn exscore(&self) -> u32 {
+        self.pgreat.saturating_mul(2).saturating_add(self.great)
+    }
+
+    /// Miss count (BP): BAD, POOR and empty POOR, plus notes that were never judged.
+    #[must_use]
+    pub const fn misses(&self) -> u32 {
+        let judged = self
+            .pgreat
+            .saturating_add(self.great)
+            .saturating_add(self.good)
+            .saturating_add(self.bad)
+            .saturating_add(self.poor);
+        self.bad
+            .saturating_add(self.poor)
+            .saturating_add(self.empty_poor)
+            .saturating_add(self.total_notes.saturating_sub(judged))
+    }
+
+    /// Score rate in percent, `EX score / (notes × 2) × 100`; `0.0` when there are no notes.
+    #[must_use]
+    pub fn score_rate(&self) -> f64 {
+        if self.total_notes == 0 {
+            return 0.0;
+        }
+        f64::from(self.exscore()) * 100.0 / (f64::from(self.total_notes) * 2.0)
+    }
+
+    /// Miss rate in percent, `misses / notes × 100`; `0.0` when there are no notes.
+    #[must_use]
+    pub fn miss_rate(&self) -> f64 {
+        if self.total_notes == 0 {
+            return 0.0;
+        }
+        f64::from(self.misses()) * 100.0 / f64::from(self.total_notes)
+    }
+}
+
+impl Trophy {
+    /// Whether a play meets this trophy's miss rate and score rate requirements.
+    #[must_use]
+    pub fn is_achieved(&self, counts: &JudgeCounts) -> bool {
+        counts.miss_rate() <= self.missrate && counts.score_rate() >= self.scorerate
+    }
+}
+
+impl CourseInfo {
+    /// Trophies of this course achieved by a play, in declaration order.
+    #[must_use]
+    pub fn achieved_trophies(&self, counts: &JudgeCounts) -> Vec<&Trophy> {
+        self.trophy
+            .iter()
+            .filter(|t| t.is_achieved(counts))
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+//! Unit tests for borrowed chart data views
+//!
+//! Checks that strings are borrowed from the source text where possible and that `into_owned` matches owned parsing.
+#![cfg(feature = "serde")]
+
+use std::borrow::Cow;
+
+use bms_table::{BmsTableData, BmsTableDataRef};
+
+const DATA_JSON: &str = r#"[
+    {
+        "level": 12,
+        "id": 1,
+        "md5": "D41D8CD98F00B204E9800998ECF8427E",
+        "sha256": "",
+        "title": "Song",
+        "artist": "Escaped \"Artist\"",
+        "url": null,
+        "comment": { "text": "raw" }
+    },
+    { "level": "!i", "title": "歌" },
+    { "title": "No level" }
+]"#;
+
+#[test]
+fn test_strings_are_borrowed() {
+    let data = BmsTableDataRef::from_json_str(DATA_JSON).unwrap();
+    let [c0, c1, c2] = data.charts.as_slice() else {
+        panic!("expected three charts: {:?}", data.charts);
+    };
+    assert!(matches!(c0.level, Cow::Owned(ref s) if s == "12"));
+    assert!(matches!(c0.title, Some(Cow::Borrowed("Song"))));
+    assert!(matches!(c0.artist, Some(Cow::Owned(ref s)) if s == "Escaped \"Artist\""));
+    assert_eq!(c0.url, None);
+    assert!(c0.md5.is_some());
+    assert_eq!(c0.sha256, None);
+
+    let [(id_key, id), (comment_key, comment)] = c0.extra.as_slice() else {
+        panic!("expected two extra fields: {:?}", c0.extra);
+    };
+    assert_eq!((id_key.as_ref(), id.get()), ("id", "1"));
+    assert_eq!(
+        (comment_key.as_ref(), comment.get()),
+        ("comment", r#"{ "text": "raw" }"#)
+    );
+
+    assert!(matches!(c1.level, Cow::Borrowed("!i")));
+    assert!(matches!(c1.title, Some(Cow::Borrowed("歌"))));
+    assert_eq!(c2.level, "");
+}
+
+#[test]
+fn test_into_owned_matches_owned_parsing() {
+    let owned: BmsTableData = serde_json::from_str(DATA_JSON).unwrap();
+    let borrowed = BmsTableDataRef::from_json_str(DATA_JSON).unwrap();
+    assert_eq!(borrowed.into_owned(), owned);
+}
+
+#[test]
+fn test_borrowed_drops_invalid_hashes() {
+    let text = r#"[{"level": 1, "md5": "abc", "sha256": 1}]"#;
+    let borrowed = BmsTableDataRef::from_json_str(text).unwrap();
+    let [chart] = borrowed.charts.as_slice() else {
+        panic!("expected one chart: {:?}", borrowed.charts);
+    };
+    assert_eq!((chart.md5, chart.sha256), (None, None));
+    let owned: BmsTableData = serde_json::from_str(text).unwrap();
+    assert_eq!(borrowed.into_owned(), owned);
+}
+
+#[test]
+fn test_borrowed_rejects_invalid_charts() {
+    assert!(BmsTableDataRef::from_json_str(r#"[{"level": true}]"#).is_err());
+    assert!(BmsTableDataRef::from_json_str(r#"[null]"#).is_err());
+    assert!(BmsTableDataRef::from_json_str(r#"{"charts": []}"#).is_err());
+}
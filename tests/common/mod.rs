@@ -0,0 +1,155 @@
+//! Shared helpers for integration tests
+
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// A response of the local test server.
+pub struct Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// Extra response headers.
+    pub headers: Vec<(&'static str, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+/// A request received by the local test server.
+pub struct Request {
+    /// Request path.
+    pub path: String,
+    /// Request headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Value of a request header, by lowercase name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Serve `routes` (path → body) with validators: each body is tagged with an `ETag` and a fixed
+/// `Last-Modified`, and a request sending the current tag back answers `304 Not Modified`.
+///
+/// Unknown paths answer `404 Not Found`.
+pub async fn serve_tagged(routes: &[(&str, &str)]) -> url::Url {
+    let routes: HashMap<String, Vec<u8>> = routes
+        .iter()
+        .map(|(path, body)| ((*path).to_string(), body.as_bytes().to_vec()))
+        .collect();
+    serve_requests(move |request| {
+        let Some(body) = routes.get(&request.path) else {
+            return Response {
+                status: 404,
+                headers: Vec::new(),
+                body: Vec::new(),
+            };
+        };
+        let etag = format!("\"{}\"", body.len());
+        let last_modified = "Wed, 21 Oct 2015 07:28:00 GMT".to_string();
+        if request.header("if-none-match") == Some(etag.as_str()) {
+            assert_eq!(
+                request.header("if-modified-since"),
+                Some(last_modified.as_str())
+            );
+            return Response {
+                status: 304,
+                headers: vec![("ETag", etag)],
+                body: Vec::new(),
+            };
+        }
+        Response {
+            status: 200,
+            headers: vec![("ETag", etag), ("Last-Modified", last_modified)],
+            body: body.clone(),
+        }
+    })
+    .await
+}
+
+/// Serve `routes` (path → body) over HTTP on a local port and return the base URL.
+///
+/// Unknown paths answer `404 Not Found`.
+pub async fn serve(routes: &[(&str, &str)]) -> url::Url {
+    let routes: HashMap<String, Vec<u8>> = routes
+        .iter()
+        .map(|(path, body)| ((*path).to_string(), body.as_bytes().to_vec()))
+        .collect();
+    serve_with(move |path| Response {
+        status: if routes.contains_key(path) { 200 } else { 404 },
+        headers: Vec::new(),
+        body: routes.get(path).cloned().unwrap_or_default(),
+    })
+    .await
+}
+
+/// Serve the responses built by `handler` from the request path over HTTP on a local port, and return the
+/// base URL.
+///
+/// Every connection is closed after one response.
+pub async fn serve_with(handler: impl Fn(&str) -> Response + Send + Sync + 'static) -> url::Url {
+    serve_requests(move |request| handler(&request.path)).await
+}
+
+/// Serve the responses built by `handler` from the whole request over HTTP on a local port, and return the
+/// base URL.
+pub async fn serve_requests(
+    handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+) -> url::Url {
+    let handler = Arc::new(handler);
+    let Ok(listener) = TcpListener::bind("127.0.0.1:0").await else {
+        panic!("failed to bind a local port");
+    };
+    let Ok(addr) = listener.local_addr() else {
+        panic!("failed to read the local address");
+    };
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let handler = Arc::clone(&handler);
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend(buf.iter().take(n)),
+                    }
+                }
+                let raw = String::from_utf8_lossy(&request);
+                let mut lines = raw.lines();
+                let path = lines
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+                let headers = lines
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                    .collect();
+                let response = handler(&Request { path, headers });
+                let mut head = format!(
+                    "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{name}: {value}\r\n"));
+                }
+                head.push_str("\r\n");
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(&response.body).await;
+            });
+        }
+    });
+    let Ok(url) = url::Url::parse(&format!("http://{addr}/")) else {
+        panic!("invalid local address: {addr}");
+    };
+    url
+}
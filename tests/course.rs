@@ -0,0 +1,112 @@
+//! Unit tests for typed course constraints
+//!
+//! Checks constraint parsing, the unknown fallback, round-trips and conflict detection.
+#![cfg(feature = "serde")]
+
+use bms_table::{
+    BmsTableHeader, CourseConstraint,
+    header::{ConstraintCategory, ConstraintConflict},
+};
+use serde_json::json;
+
+#[test]
+fn test_constraints_parse_known_and_unknown() {
+    let header: BmsTableHeader = serde_json::from_value(json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{
+            "name": "Course",
+            "constraint": ["grade_mirror", "gauge_lr2", "ln", "no_mine"],
+            "md5": []
+        }]
+    }))
+    .unwrap();
+
+    let [group] = header.course.as_slice() else {
+        panic!("expected one group: {:?}", header.course);
+    };
+    let [course] = group.as_slice() else {
+        panic!("expected one course: {group:?}");
+    };
+    assert_eq!(
+        course.constraint,
+        vec![
+            CourseConstraint::GradeMirror,
+            CourseConstraint::GaugeLr2,
+            CourseConstraint::Ln,
+            CourseConstraint::Unknown("no_mine".to_string()),
+        ]
+    );
+    assert_eq!(
+        course.constraint.last().map(CourseConstraint::category),
+        Some(ConstraintCategory::Unknown)
+    );
+    assert!(course.constraint_conflicts().is_empty());
+
+    let value = serde_json::to_value(&header).unwrap();
+    assert_eq!(
+        value.pointer("/course/0/0/constraint"),
+        Some(&json!(["grade_mirror", "gauge_lr2", "ln", "no_mine"]))
+    );
+}
+
+#[test]
+fn test_constraint_strings_are_kept_verbatim() {
+    for s in ["grade", "no_speed", "gauge_24k", "hcn", "GAUGE_LR2", ""] {
+        let c = CourseConstraint::from(s);
+        assert_eq!(c.as_str(), s);
+        assert_eq!(c.to_string(), s);
+        assert_eq!(serde_json::to_value(&c).unwrap(), json!(s));
+    }
+    assert!(matches!(
+        CourseConstraint::from("GAUGE_LR2"),
+        CourseConstraint::Unknown(_)
+    ));
+}
+
+#[test]
+fn test_constraint_conflicts() {
+    let header: BmsTableHeader = serde_json::from_value(json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{
+            "name": "Course",
+            "constraint": ["gauge_lr2", "grade", "gauge_7k", "gauge_lr2", "ln", "cn", "no_good", "x", "y"],
+            "md5": []
+        }]
+    }))
+    .unwrap();
+
+    let conflicts = header
+        .course
+        .iter()
+        .flatten()
+        .flat_map(bms_table::CourseInfo::constraint_conflicts)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        conflicts,
+        vec![
+            ConstraintConflict {
+                category: ConstraintCategory::Gauge,
+                constraints: vec![CourseConstraint::GaugeLr2, CourseConstraint::Gauge7Keys],
+            },
+            ConstraintConflict {
+                category: ConstraintCategory::LongNote,
+                constraints: vec![CourseConstraint::Ln, CourseConstraint::Cn],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_constraint_non_string_is_rejected() {
+    let result = serde_json::from_value::<BmsTableHeader>(json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{ "name": "Course", "constraint": [1], "md5": [] }]
+    }));
+    assert!(result.is_err());
+}
@@ -0,0 +1,61 @@
+//! Unit tests for character encoding detection
+//!
+//! Checks the detection order (BOM, `Content-Type`, `<meta>` charset, heuristic) of `Shift_JIS`, EUC-JP and UTF-8 text.
+#![cfg(feature = "scraper")]
+
+use bms_table::fetch::encoding::{CharsetSource, decode_text};
+use encoding_rs::{EUC_JP, SHIFT_JIS};
+
+/// Japanese text shared by the tests.
+const TEXT: &str = "第2発狂難易度表 ★1 ｱｲｳ";
+
+#[test]
+fn test_bom_wins() {
+    let mut bytes = b"\xEF\xBB\xBF".to_vec();
+    bytes.extend_from_slice(TEXT.as_bytes());
+    let decoded = decode_text(&bytes, Some("text/html; charset=Shift_JIS"));
+    assert_eq!(decoded.text, TEXT);
+    assert_eq!(decoded.encoding, "UTF-8");
+    assert_eq!(decoded.source, CharsetSource::Bom);
+}
+
+#[test]
+fn test_content_type_before_meta() {
+    let (body, _, _) = EUC_JP.encode(TEXT);
+    let mut page = b"<meta charset=\"utf-8\">".to_vec();
+    page.extend_from_slice(&body);
+    let decoded = decode_text(&page, Some("text/html; charset=\"EUC-JP\""));
+    assert_eq!(decoded.encoding, "EUC-JP");
+    assert_eq!(decoded.source, CharsetSource::ContentType);
+    assert!(decoded.text.ends_with(TEXT));
+}
+
+#[test]
+fn test_meta_charset() {
+    let (body, _, _) = SHIFT_JIS.encode(TEXT);
+    let mut page =
+        b"<html><head><META http-equiv=\"Content-Type\" content=\"text/html; charset=x-sjis\"></head><body>"
+            .to_vec();
+    page.extend_from_slice(&body);
+    // An unknown charset label in the header is ignored.
+    let decoded = decode_text(&page, Some("text/html; charset=unknown"));
+    assert_eq!(decoded.encoding, "Shift_JIS");
+    assert_eq!(decoded.source, CharsetSource::MetaCharset);
+    assert!(decoded.text.ends_with(TEXT));
+}
+
+#[test]
+fn test_heuristic() {
+    let json = format!(r#"[{{"title": "{TEXT}", "level": "1"}}]"#);
+    let decoded = decode_text(json.as_bytes(), None);
+    assert_eq!(decoded.encoding, "UTF-8");
+    assert_eq!(decoded.source, CharsetSource::Heuristic);
+
+    for encoding in [SHIFT_JIS, EUC_JP] {
+        let (bytes, _, _) = encoding.encode(&json);
+        let guessed = decode_text(&bytes, Some("application/json"));
+        assert_eq!(guessed.encoding, encoding.name());
+        assert_eq!(guessed.source, CharsetSource::Heuristic);
+        assert_eq!(guessed.text, json);
+    }
+}
@@ -0,0 +1,88 @@
+//! Unit tests for the synchronous fetcher (requires the `blocking` feature)
+//!
+//! The local test server runs on its own runtime; the fetcher itself is called from plain synchronous code.
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use bms_table::fetch::blocking::Fetcher;
+
+#[test]
+fn test_blocking_fetch_table_follows_redirects() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let base = runtime.block_on(common::serve_with(|path| {
+        let (status, headers, body) = match path {
+            "/old" => (301, vec![("Location", "/new/".to_string())], ""),
+            "/new/" => (
+                200,
+                Vec::new(),
+                r#"<meta name="bmstable" content="header.json">"#,
+            ),
+            "/new/header.json" => (
+                200,
+                Vec::new(),
+                r#"{"name": "Test", "symbol": "t", "data_url": "data.json"}"#,
+            ),
+            "/new/data.json" => (200, Vec::new(), r#"[{"level": "1", "title": "Song"}]"#),
+            _ => (404, Vec::new(), ""),
+        };
+        common::Response {
+            status,
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    }));
+
+    let fetcher = Fetcher::lenient().unwrap();
+    let fetched = fetcher.fetch_table(base.join("old").unwrap()).unwrap();
+    assert_eq!(fetched.table.header.name, "Test");
+    assert_eq!(fetched.table.data.charts.len(), 1);
+    assert_eq!(
+        fetched.raw.data_json_url,
+        base.join("new/data.json").unwrap()
+    );
+}
+
+#[test]
+fn test_blocking_fetch_table_list() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let base = runtime.block_on(common::serve(&[(
+        "/list.json",
+        r#"[{"name": "Table", "symbol": "t", "url": "https://example.com/table.html"}]"#,
+    )]));
+
+    let fetcher = Fetcher::lenient().unwrap();
+    let fetched = fetcher
+        .fetch_table_list(base.join("list.json").unwrap())
+        .unwrap();
+    let [table] = fetched.tables.as_slice() else {
+        panic!("expected one table: {:?}", fetched.tables);
+    };
+    assert_eq!(table.name, "Table");
+
+    let Err(err) = fetcher.fetch_table(base.join("missing.html").unwrap()) else {
+        panic!("a missing page must fail");
+    };
+    assert!(matches!(err, bms_table::Error::Status { status: 404, .. }));
+}
+
+#[test]
+fn test_blocking_fetch_table_if_modified() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let base = runtime.block_on(common::serve_tagged(&[
+        (
+            "/header.json",
+            r#"{"name": "Test", "symbol": "t", "data_url": "data.json"}"#,
+        ),
+        ("/data.json", r#"[{"level": "1", "title": "Song"}]"#),
+    ]));
+    let url = base.join("header.json").unwrap();
+
+    let fetcher = Fetcher::builder().build_blocking().unwrap();
+    let fetched = fetcher.fetch_table(url.clone()).unwrap();
+    assert_eq!(fetched.validators.header, fetched.validators.web_page);
+    let update = fetcher
+        .fetch_table_if_modified(url, &fetched.validators)
+        .unwrap();
+    assert!(matches!(update, bms_table::fetch::TableUpdate::NotModified));
+}
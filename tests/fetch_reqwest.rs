@@ -3,6 +3,8 @@
 //! Primarily checks error paths and robustness, e.g., errors for invalid URLs.
 #![cfg(feature = "reqwest")]
 
+mod common;
+
 // Network-related tests (when the reqwest feature is enabled)
 
 #[tokio::test]
@@ -12,3 +14,770 @@ async fn test_fetch_table_invalid_url() {
     let result = fetcher.fetch_table(url).await;
     assert!(result.is_err());
 }
+
+#[tokio::test]
+async fn test_fetch_table_unparsable_url_is_permanent() {
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let Err(err) = fetcher.fetch_table("not a url").await else {
+        panic!("an unparsable url must not be fetched");
+    };
+    assert!(matches!(err, bms_table::Error::InvalidUrl { .. }));
+    assert!(!err.is_transient());
+}
+
+/// Header JSON served by the local test server.
+const HEADER_JSON: &str = r#"{"name": "Test", "symbol": "t", "data_url": "data.json"}"#;
+
+#[tokio::test]
+async fn test_fetch_table_skips_invalid_header_candidates() {
+    let page = r#"<html><head>
+        <meta name="bmstable" content="missing.json">
+        <link rel="bmstable" href="page.html">
+        </head><body><a href="header.json">header</a></body></html>"#;
+    let base = common::serve(&[
+        ("/", page),
+        ("/page.html", page),
+        ("/header.json", HEADER_JSON),
+        ("/data.json", r#"[{"level": "1", "title": "Song"}]"#),
+    ])
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(fetched.table.header.name, "Test");
+    assert_eq!(fetched.table.data.charts.len(), 1);
+    assert_eq!(
+        fetched.raw.header_json_url,
+        base.join("header.json").unwrap()
+    );
+
+    let [missing, cycled] = fetched.header_attempts.as_slice() else {
+        panic!(
+            "expected two rejected candidates: {:?}",
+            fetched.header_attempts
+        );
+    };
+    assert_eq!(missing.candidate.url, "missing.json");
+    assert!(matches!(
+        missing.error,
+        bms_table::Error::Status { status: 404, .. }
+    ));
+    assert_eq!(cycled.url, Some(base.join("page.html").unwrap()));
+    assert!(matches!(
+        cycled.error,
+        bms_table::Error::CycledHeader { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_table_reports_every_rejected_candidate() {
+    let page = r#"<html><head><meta name="bmstable" content="a.json"></head>
+        <body><a href="b_header.json">next</a><a href="a.json">again</a></body></html>"#;
+    let base = common::serve(&[("/", page), ("/b_header.json", "{}")]).await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let Err(err) = fetcher.fetch_table(base.clone()).await else {
+        panic!("no candidate is a valid header");
+    };
+    let bms_table::Error::HeaderCandidates { url, attempts } = &err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(url, &base);
+    // The repeated `a.json` link and the raw text match of `b_header.json` are only tried once.
+    let [a, b] = attempts.as_slice() else {
+        panic!("expected two attempts: {attempts:?}");
+    };
+    assert_eq!(a.candidate.url, "a.json");
+    assert_eq!(b.candidate.url, "b_header.json");
+    assert!(matches!(b.error, bms_table::Error::HeaderJson { .. }));
+    assert!(matches!(
+        a.error,
+        bms_table::Error::Status { status: 404, .. }
+    ));
+    assert!(!err.is_transient());
+}
+
+#[tokio::test]
+async fn test_fetch_table_with_inline_header() {
+    let page =
+        r#"<script>var header = { name: "Inline", symbol: "i", data_url: "data.json" };</script>"#;
+    let base = common::serve(&[
+        ("/", page),
+        ("/data.json", r#"[{"level": 3, "title": "Song"}]"#),
+    ])
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(fetched.table.header.name, "Inline");
+    assert_eq!(fetched.raw.header_json_url, base);
+    assert_eq!(fetched.raw.data_json_url, base.join("data.json").unwrap());
+    let [chart] = fetched.table.data.charts.as_slice() else {
+        panic!("expected one chart: {:?}", fetched.table.data.charts);
+    };
+    assert_eq!(chart.level, "3");
+}
+
+#[tokio::test]
+async fn test_fetch_table_decodes_shift_jis() {
+    let base = common::serve_with(|path| {
+        let (body, content_type) = match path {
+            "/" => (
+                r#"<meta charset="Shift_JIS"><meta name="bmstable" content="header.json">難易度表"#,
+                "text/html",
+            ),
+            "/header.json" => (
+                r#"{"name": "発狂難易度表", "symbol": "★", "data_url": "data.json"}"#,
+                "application/json",
+            ),
+            _ => (r#"[{"level": "1", "title": "曲名"}]"#, "application/json"),
+        };
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(body);
+        common::Response {
+            status: 200,
+            headers: vec![("Content-Type", content_type.to_string())],
+            body: bytes.into_owned(),
+        }
+    })
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher.fetch_table(base).await.unwrap();
+    assert_eq!(fetched.table.header.name, "発狂難易度表");
+    assert_eq!(fetched.table.header.symbol, "★");
+    let [chart] = fetched.table.data.charts.as_slice() else {
+        panic!("expected one chart: {:?}", fetched.table.data.charts);
+    };
+    assert_eq!(chart.title.as_deref(), Some("曲名"));
+    assert_eq!(fetched.raw.header_encoding, "Shift_JIS");
+    assert_eq!(fetched.raw.data_encoding, "Shift_JIS");
+}
+
+#[tokio::test]
+async fn test_fetch_table_list_falls_back_to_html() {
+    let page = r#"<table>
+        <tr><th>Name</th><th>Symbol</th><th>Tag</th></tr>
+        <tr><td><a href="table.html">Table</a></td><td>t</td><td>tag</td></tr>
+    </table>"#;
+    let base = common::serve(&[("/", page), ("/broken", "<p>not a list</p>")]).await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher.fetch_table_list(base.clone()).await.unwrap();
+    let [table] = fetched.tables.as_slice() else {
+        panic!("expected one table: {:?}", fetched.tables);
+    };
+    assert_eq!(table.name, "Table");
+    assert_eq!(table.url, base.join("table.html").unwrap());
+    let raw: bms_table::BmsTableList = serde_json::from_str(&fetched.raw_json).unwrap();
+    assert_eq!(raw.listes, fetched.tables);
+
+    let Err(err) = fetcher.fetch_table_list(base.join("broken").unwrap()).await else {
+        panic!("a page without a list must fail");
+    };
+    assert!(matches!(err, bms_table::Error::TableListJson { .. }));
+}
+
+#[tokio::test]
+async fn test_fetch_table_with_jsonp_data() {
+    let header = r#"{"name": "Test", "symbol": "t", "data_url": "data.js"}"#;
+    let base = common::serve(&[
+        ("/header.json", header),
+        (
+            "/data.js",
+            "callback([{\"level\": \"1\", \"title\": \"Song\"},]);",
+        ),
+    ])
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher
+        .fetch_table(base.join("header.json").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(fetched.table.data.charts.len(), 1);
+    assert_eq!(
+        fetched.raw.data_raw,
+        "[{\"level\": \"1\", \"title\": \"Song\"}]"
+    );
+}
+
+#[test]
+fn test_fetcher_builder_rejects_invalid_settings() {
+    use bms_table::fetch::reqwest::FetcherBuilder;
+
+    let Err(header_err) = FetcherBuilder::new().user_agent("bad\nagent").build() else {
+        panic!("a user agent with a newline must be rejected");
+    };
+    assert!(matches!(header_err, bms_table::Error::Client { .. }));
+    let Err(proxy_err) = FetcherBuilder::new().proxy("not a proxy url").build() else {
+        panic!("an unparsable proxy url must be rejected");
+    };
+    assert!(matches!(proxy_err, bms_table::Error::Client { .. }));
+}
+
+#[tokio::test]
+async fn test_fetcher_builder_stage_timeout_and_redirects() {
+    let base = common::serve_with(|path| {
+        let (status, headers, body) = match path {
+            "/moved" => (301, vec![("Location", "/header.json".to_string())], ""),
+            "/header.json" => (200, Vec::new(), HEADER_JSON),
+            _ => {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                (200, Vec::new(), "[]")
+            }
+        };
+        common::Response {
+            status,
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    })
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::builder()
+        .stage_timeout(
+            bms_table::error::FetchStage::Data,
+            std::time::Duration::from_millis(50),
+        )
+        .max_redirects(0)
+        .build()
+        .unwrap();
+    let Err(timeout_err) = fetcher.fetch_table(base.join("header.json").unwrap()).await else {
+        panic!("the data request must time out");
+    };
+    assert!(matches!(
+        timeout_err,
+        bms_table::Error::Network {
+            stage: bms_table::error::FetchStage::Data,
+            transient: true,
+            ..
+        }
+    ));
+    let Err(redirect_err) = fetcher.fetch_table(base.join("moved").unwrap()).await else {
+        panic!("redirects are disabled");
+    };
+    assert!(matches!(
+        redirect_err,
+        bms_table::Error::Status { status: 301, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_table_if_modified() {
+    let page = r#"<meta name="bmstable" content="header.json">"#;
+    let data = r#"[{"level": "1", "title": "Song"}]"#;
+    let base = common::serve_tagged(&[
+        ("/", page),
+        ("/header.json", HEADER_JSON),
+        ("/data.json", data),
+    ])
+    .await;
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::builder()
+        .build()
+        .unwrap();
+    let fetched = fetcher.fetch_table(base.clone()).await.unwrap();
+    let validators = &fetched.validators;
+    let Some(header) = &validators.header else {
+        panic!("the header validators must be recorded: {validators:?}");
+    };
+    assert_eq!(header.url, base.join("header.json").unwrap());
+    assert_eq!(
+        header.last_modified.as_deref(),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT")
+    );
+
+    let update = fetcher
+        .fetch_table_if_modified(base.clone(), validators)
+        .await
+        .unwrap();
+    assert!(matches!(update, bms_table::fetch::TableUpdate::NotModified));
+
+    // Validators recorded for another table are not sent.
+    let other = base.join("header.json").unwrap();
+    let other_update = fetcher
+        .fetch_table_if_modified(other, validators)
+        .await
+        .unwrap();
+    assert!(matches!(
+        other_update,
+        bms_table::fetch::TableUpdate::Modified(_)
+    ));
+}
+
+/// Fresh cache directory for one test.
+fn cache_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("bms-table-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn test_fetch_table_from_cache() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    use bms_table::fetch::{CacheMode, CachePolicy, DiskCache, reqwest::Fetcher};
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let failing = Arc::new(AtomicBool::new(false));
+    let (server_hits, server_failing) = (Arc::clone(&hits), Arc::clone(&failing));
+    let base = common::serve_with(move |path| {
+        server_hits.fetch_add(1, Ordering::SeqCst);
+        let (status, body) = match path {
+            _ if server_failing.load(Ordering::SeqCst) => (503, ""),
+            "/" => (200, r#"<meta name="bmstable" content="header.json">"#),
+            "/header.json" => (200, HEADER_JSON),
+            "/data.json" => (200, r#"[{"level": "1", "title": "Song"}]"#),
+            _ => (404, ""),
+        };
+        common::Response {
+            status,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    })
+    .await;
+    let cache = DiskCache::new(cache_dir("cache"));
+    let fetcher_with = |policy: CachePolicy| {
+        Fetcher::builder()
+            .cache(cache.clone(), policy)
+            .build()
+            .unwrap()
+    };
+
+    // A fresh copy is served without touching the network.
+    let fetcher = fetcher_with(CachePolicy::new());
+    fetcher.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+    let cached = fetcher.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(cached.table.data.charts.len(), 1);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // Offline, only cached documents are served, whatever their age.
+    let offline = fetcher_with(
+        CachePolicy::new()
+            .mode(CacheMode::Offline)
+            .ttl(std::time::Duration::ZERO),
+    );
+    let offline_table = offline.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(offline_table.table.header.name, "Test");
+    let Err(err) = offline.fetch_table(base.join("other.html").unwrap()).await else {
+        panic!("an uncached page must not be fetched offline");
+    };
+    assert!(matches!(err, bms_table::Error::NotCached { .. }));
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // A stale copy stands in for a failing server only when asked to.
+    failing.store(true, Ordering::SeqCst);
+    let stale = fetcher_with(
+        CachePolicy::new()
+            .mode(CacheMode::StaleOnError)
+            .ttl(std::time::Duration::ZERO),
+    );
+    let stale_table = stale.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(stale_table.table.data.charts.len(), 1);
+    let normal =
+        fetcher_with(CachePolicy::new().table_ttl(base.clone(), std::time::Duration::ZERO));
+    let Err(failed) = normal.fetch_table(base.clone()).await else {
+        panic!("a stale copy must not hide a server error by default");
+    };
+    assert!(matches!(
+        failed,
+        bms_table::Error::Status { status: 503, .. }
+    ));
+    let _ = std::fs::remove_dir_all(cache.dir());
+}
+
+#[test]
+fn test_cache_concurrent_puts() {
+    use bms_table::fetch::{CacheEntry, DiskCache};
+
+    let cache = DiskCache::new(cache_dir("concurrent"));
+    let url = url::Url::parse("https://example.com/data.json").unwrap();
+    let bodies: Vec<Vec<u8>> = (0..8).map(|i| vec![b'a' + i; 4096]).collect();
+    std::thread::scope(|scope| {
+        for body in &bodies {
+            let (cache, url) = (&cache, &url);
+            scope.spawn(move || {
+                for _ in 0..16 {
+                    let entry = CacheEntry {
+                        url: url.clone(),
+                        final_url: url.clone(),
+                        fetched_at: std::time::SystemTime::now(),
+                        content_type: None,
+                        etag: None,
+                        last_modified: None,
+                        body: body.clone(),
+                    };
+                    cache.put(&entry).unwrap();
+                }
+            });
+        }
+    });
+
+    // Every put completed a whole body, and no temporary file was left behind.
+    let entry = cache.get(&url).unwrap();
+    assert!(bodies.contains(&entry.body));
+    let leftovers = std::fs::read_dir(cache.dir())
+        .unwrap()
+        .filter(|file| {
+            file.as_ref()
+                .is_ok_and(|file| file.path().extension().is_some_and(|ext| ext == "tmp"))
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+    let _ = std::fs::remove_dir_all(cache.dir());
+}
+
+#[tokio::test]
+async fn test_fetch_table_revalidates_cache() {
+    use bms_table::fetch::{CachePolicy, DiskCache, TableUpdate, reqwest::Fetcher};
+
+    let base = common::serve_tagged(&[
+        ("/", r#"<meta name="bmstable" content="header.json">"#),
+        ("/header.json", HEADER_JSON),
+        ("/data.json", r#"[{"level": "1", "title": "Song"}]"#),
+    ])
+    .await;
+    let cache = DiskCache::new(cache_dir("revalidate"));
+    let fetcher = Fetcher::builder()
+        .cache(
+            cache.clone(),
+            CachePolicy::new().ttl(std::time::Duration::ZERO),
+        )
+        .build()
+        .unwrap();
+
+    let fetched = fetcher.fetch_table(base.clone()).await.unwrap();
+    // Stale copies are revalidated, and answered from the cache on `304 Not Modified`.
+    let revalidated = fetcher.fetch_table(base.clone()).await.unwrap();
+    assert_eq!(revalidated.table, fetched.table);
+    let entry = cache.get(&base.join("data.json").unwrap()).unwrap();
+    assert_eq!(entry.etag.as_deref(), Some("\"33\""));
+
+    let update = fetcher
+        .fetch_table_if_modified(base, &fetched.validators)
+        .await
+        .unwrap();
+    assert!(matches!(update, TableUpdate::NotModified));
+    let _ = std::fs::remove_dir_all(cache.dir());
+}
+
+#[tokio::test]
+async fn test_fetchers_do_not_retry_by_default() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use bms_table::fetch::reqwest::Fetcher;
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server_hits = Arc::clone(&hits);
+    let base = common::serve_with(move |_| {
+        server_hits.fetch_add(1, Ordering::SeqCst);
+        common::Response {
+            status: 503,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    })
+    .await;
+    let fetchers = [
+        Fetcher::new(reqwest::Client::new()),
+        Fetcher::builder().build().unwrap(),
+        Fetcher::lenient().unwrap(),
+    ];
+    for (i, fetcher) in fetchers.iter().enumerate() {
+        assert!(fetcher.fetch_table(base.clone()).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), i + 1);
+    }
+}
+
+#[tokio::test]
+async fn test_fetch_retries_transient_failures() {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use bms_table::fetch::{HttpResponse, RetryPolicy, reqwest::Fetcher};
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server_hits = Arc::clone(&hits);
+    let base = common::serve_with(move |path| {
+        let hit = server_hits.fetch_add(1, Ordering::SeqCst);
+        let (status, headers) = match path {
+            "/list.json" if hit < 2 => (503, vec![("Retry-After", "0".to_string())]),
+            "/list.json" => (200, Vec::new()),
+            _ => (404, Vec::new()),
+        };
+        common::Response {
+            status,
+            headers,
+            body: br#"[{"name": "Table", "symbol": "t", "url": "https://example.com/"}]"#.to_vec(),
+        }
+    })
+    .await;
+    let list_url = base.join("list.json").unwrap();
+    let retry = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_secs(1));
+    let fetcher = Fetcher::builder().retry(retry.clone()).build().unwrap();
+
+    // Two `503 Service Unavailable` answers, then the list.
+    let fetched = fetcher.fetch_table_list(list_url.clone()).await.unwrap();
+    assert_eq!(fetched.tables.len(), 1);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // Permanent failures are not retried.
+    let Err(err) = fetcher
+        .fetch_table(base.join("missing.html").unwrap())
+        .await
+    else {
+        panic!("a missing page must fail");
+    };
+    assert!(matches!(err, bms_table::Error::Status { status: 404, .. }));
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+
+    // `Retry-After` dates in the past retry at once; waits beyond the maximum backoff are not retried.
+    let unavailable = |retry_after: &str| {
+        Ok(HttpResponse::new(list_url.clone(), 503, Vec::new()).with_retry_after(retry_after))
+    };
+    let past = unavailable("Wed, 21 Oct 2015 07:28:00 GMT");
+    assert_eq!(retry.retry_delay(1, &past), Some(Duration::ZERO));
+    assert_eq!(retry.retry_delay(1, &unavailable("3600")), None);
+    // An unparseable `Retry-After` falls back to the computed backoff.
+    for value in ["soon", "Sun, 06 Nov 18446744073709551615 08:49:37 GMT"] {
+        let garbled = retry.retry_delay(1, &unavailable(value));
+        assert!(
+            garbled.is_some_and(|delay| delay <= Duration::from_millis(1)),
+            "delay for {value}: {garbled:?}"
+        );
+    }
+    let far = unavailable("Fri, 31 Dec 9999 23:59:59 GMT");
+    assert_eq!(retry.retry_delay(1, &far), None);
+    assert_eq!(retry.retry_delay(3, &unavailable("0")), None);
+    assert_eq!(RetryPolicy::none().retry_delay(1, &unavailable("0")), None);
+}
+
+#[tokio::test]
+async fn test_fetcher_limits_requests_per_host() {
+    use std::time::{Duration, Instant};
+
+    let base = common::serve(&[(
+        "/list.json",
+        r#"[{"name": "Table", "symbol": "t", "url": "https://example.com/"}]"#,
+    )])
+    .await;
+    let list_url = base.join("list.json").unwrap();
+    let fetcher = bms_table::fetch::reqwest::Fetcher::builder()
+        .max_concurrent_per_host(1)
+        .min_delay_per_host(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let clone = fetcher.clone();
+
+    // The delay is shared by the clones: the third request starts 200 ms after the first.
+    let started = Instant::now();
+    let (first, second, third) = tokio::join!(
+        fetcher.fetch_table_list(list_url.clone()),
+        clone.fetch_table_list(list_url.clone()),
+        fetcher.fetch_table_list(list_url.clone()),
+    );
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    for fetched in [first, second, third] {
+        assert_eq!(fetched.unwrap().tables.len(), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_fetch_tables_streams_every_result() {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use futures_util::StreamExt;
+    use tokio::io::AsyncReadExt;
+
+    let base = common::serve(&[
+        ("/a/header.json", HEADER_JSON),
+        ("/a/data.json", r#"[{"level": "1", "title": "Song"}]"#),
+        ("/b/header.json", HEADER_JSON),
+        (
+            "/b/data.json",
+            r#"[{"level": "1", "title": "A"}, {"level": "2", "title": "B"}]"#,
+        ),
+    ])
+    .await;
+    let urls: Vec<url::Url> = ["a/header.json", "b/header.json", "missing.html"]
+        .iter()
+        .map(|path| base.join(path).unwrap())
+        .collect();
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let mut results: Vec<_> = fetcher.fetch_tables(urls.clone(), 2).collect().await;
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let [(a_url, a), (b_url, b), (missing_url, missing)] = results.as_slice() else {
+        panic!("expected one result per url, got {}", results.len());
+    };
+    assert_eq!(
+        vec![a_url.clone(), b_url.clone(), missing_url.clone()],
+        urls
+    );
+    assert_eq!(a.as_ref().unwrap().table.data.charts.len(), 1);
+    assert_eq!(b.as_ref().unwrap().table.data.charts.len(), 2);
+    assert!(matches!(
+        missing,
+        Err(bms_table::Error::Status { status: 404, .. })
+    ));
+
+    // Dropping the stream early cancels the running fetches and never starts the others.
+    let Ok(hanging) = tokio::net::TcpListener::bind("127.0.0.1:0").await else {
+        panic!("failed to bind a local port");
+    };
+    let hanging_url =
+        url::Url::parse(&format!("http://{}/", hanging.local_addr().unwrap())).unwrap();
+    // Accept the request but never answer it, and report when the client gives up on it.
+    let hung_up = tokio::spawn(async move {
+        let (mut stream, _) = hanging.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        while stream.read(&mut buf).await.is_ok_and(|n| n > 0) {}
+    });
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server_hits = Arc::clone(&hits);
+    let counted = common::serve_with(move |path| {
+        server_hits.fetch_add(1, Ordering::SeqCst);
+        common::Response {
+            status: 200,
+            headers: Vec::new(),
+            body: if path.ends_with("header.json") {
+                HEADER_JSON
+            } else {
+                "[]"
+            }
+            .as_bytes()
+            .to_vec(),
+        }
+    })
+    .await;
+    let stream_urls = vec![
+        a_url.clone(),
+        hanging_url,
+        counted.join("header.json").unwrap(),
+    ];
+    let mut stream = fetcher.fetch_tables(stream_urls, 2);
+    let (first_url, first) = stream.next().await.unwrap();
+    assert_eq!(&first_url, a_url);
+    assert!(first.is_ok());
+    drop(stream);
+    let closed = tokio::time::timeout(Duration::from_secs(5), hung_up).await;
+    assert!(closed.is_ok(), "the running fetch must be cancelled");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(hits.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_fetch_all_tables_of_a_list() {
+    use std::sync::{Arc, OnceLock};
+
+    use bms_table::fetch::{ListFilter, ListSummary};
+
+    // The list points back to the server, whose port is only known once it runs.
+    let list_json = Arc::new(OnceLock::<String>::new());
+    let served_list = Arc::clone(&list_json);
+    let base = common::serve_with(move |path| {
+        let body = match path {
+            "/list.json" => served_list.get().cloned().unwrap_or_default(),
+            "/sp/header.json" => HEADER_JSON.to_string(),
+            "/dp/header.json" => {
+                r#"{"name": "DP", "symbol": "dp", "data_url": "data.json"}"#.to_string()
+            }
+            "/sp/data.json" | "/dp/data.json" => r#"[{"level": "1", "title": "Song"}]"#.to_string(),
+            _ => String::new(),
+        };
+        common::Response {
+            status: if body.is_empty() { 404 } else { 200 },
+            headers: Vec::new(),
+            body: body.into_bytes(),
+        }
+    })
+    .await;
+    let entry = |symbol: &str, path: &str, tag: &str| {
+        serde_json::json!({
+            "name": symbol,
+            "symbol": symbol,
+            "url": base.join(path).unwrap(),
+            "tag1": tag,
+            "tag_order": "1",
+        })
+    };
+    let list = serde_json::json!([
+        entry("sp", "sp/header.json", "SP"),
+        entry("dp", "dp/header.json", "DP"),
+        entry("gone", "gone/header.json", "SP"),
+    ]);
+    list_json.set(list.to_string()).unwrap();
+
+    let fetcher = bms_table::fetch::reqwest::Fetcher::lenient().unwrap();
+    let fetched = fetcher
+        .fetch_all_tables(
+            base.join("list.json").unwrap(),
+            &ListFilter::new().tag("sp"),
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fetched.summary(),
+        ListSummary {
+            listed: 3,
+            skipped: 1,
+            fetched: 1,
+            failed: 1,
+        }
+    );
+    let [(info, table)] = fetched.fetched().collect::<Vec<_>>()[..] else {
+        panic!("expected one fetched table");
+    };
+    assert_eq!(
+        (info.symbol.as_str(), table.table.header.name.as_str()),
+        ("sp", "Test")
+    );
+    let [(gone, error)] = fetched.failed().collect::<Vec<_>>()[..] else {
+        panic!("expected one failed table");
+    };
+    assert_eq!(gone.symbol, "gone");
+    assert!(matches!(
+        error,
+        bms_table::Error::Status { status: 404, .. }
+    ));
+    assert_eq!(
+        fetched.skipped.first().map(|i| i.symbol.as_str()),
+        Some("dp")
+    );
+
+    let parsed: bms_table::BmsTableList = serde_json::from_value(list).unwrap();
+    let by_symbol = fetcher
+        .fetch_listed_tables(parsed, &ListFilter::new().symbol("dp"), 1)
+        .await;
+    assert_eq!(by_symbol.summary().fetched, 1);
+    assert_eq!(by_symbol.summary().skipped, 2);
+
+    // A list that cannot be fetched fails as a whole.
+    let Err(err) = fetcher
+        .fetch_all_tables(base.join("missing.json").unwrap(), &ListFilter::new(), 2)
+        .await
+    else {
+        panic!("a missing list must fail");
+    };
+    assert!(matches!(err, bms_table::Error::Status { status: 404, .. }));
+}
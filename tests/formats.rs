@@ -0,0 +1,153 @@
+//! Unit tests for non-JSON data formats
+//!
+//! Checks that headers and chart data load from YAML (text) and `MessagePack` (binary), and that tables round-trip through them.
+#![cfg(feature = "serde")]
+
+use bms_table::{BmsTable, BmsTableData, BmsTableHeader, CourseConstraint, KeyMode};
+
+const HEADER_YAML: &str = r#"
+name: Test Table
+symbol: t
+data_url: charts.json
+mode: beat-7k
+last_update: 20240101
+level_order: [0, 1, 2.5, "!i"]
+course:
+  - name: Course 1
+    constraint: [grade_mirror, gauge_lr2]
+    trophy:
+      - { name: goldmedal, missrate: 2, scorerate: 85.5 }
+    md5: [d41d8cd98f00b204e9800998ecf8427e]
+    charts:
+      - { title: No level }
+      - { level: 3, title: Has level }
+custom: { nested: true }
+"#;
+
+const DATA_YAML: &str = r#"
+- level: 12
+  title: Song
+  md5: D41D8CD98F00B204E9800998ECF8427E
+  id: 1
+- level: "!i"
+  title: Other
+"#;
+
+#[test]
+fn test_header_from_yaml() {
+    let header: BmsTableHeader = serde_yaml::from_str(HEADER_YAML).unwrap();
+    assert_eq!(header.level_order, ["0", "1", "2.5", "!i"]);
+    assert_eq!(header.last_update.as_deref(), Some("20240101"));
+    assert!(header.extra.contains_key("custom"));
+
+    let [group] = header.course.as_slice() else {
+        panic!("expected one group: {:?}", header.course);
+    };
+    let [course] = group.as_slice() else {
+        panic!("expected one course: {group:?}");
+    };
+    assert_eq!(
+        course.constraint,
+        [CourseConstraint::GradeMirror, CourseConstraint::GaugeLr2]
+    );
+    let [no_level, has_level, from_md5] = course.charts.as_slice() else {
+        panic!("expected three charts: {:?}", course.charts);
+    };
+    assert_eq!(no_level.level, "0");
+    assert_eq!(has_level.level, "3");
+    assert_eq!(from_md5.level, "0");
+    assert!(from_md5.md5.is_some());
+}
+
+#[test]
+fn test_data_from_yaml() {
+    let data: BmsTableData = serde_yaml::from_str(DATA_YAML).unwrap();
+    let [c0, c1] = data.charts.as_slice() else {
+        panic!("expected two charts: {:?}", data.charts);
+    };
+    assert_eq!(c0.level, "12");
+    assert_eq!(
+        c0.md5.map(|h| h.to_string()).as_deref(),
+        Some("d41d8cd98f00b204e9800998ecf8427e")
+    );
+    assert_eq!(c1.level, "!i");
+
+    assert!(serde_yaml::from_str::<BmsTableData>("- level: true").is_err());
+}
+
+#[test]
+fn test_invalid_hashes_from_yaml() {
+    let data: BmsTableData = serde_yaml::from_str(
+        "- { level: 1, md5: abc, sha256: 12 }\n- { level: 2, md5: [a, b], sha256: { x: 1 } }\n- { level: 3, md5: ~ }",
+    )
+    .unwrap();
+    assert!(
+        data.charts
+            .iter()
+            .all(|chart| chart.md5.is_none() && chart.sha256.is_none())
+    );
+
+    let header: BmsTableHeader = serde_yaml::from_str(
+        "name: T\nsymbol: t\ndata_url: d.json\ncourse:\n  - name: C\n    md5: [d41d8cd98f00b204e9800998ecf8427e, bad, 3, ~]",
+    )
+    .unwrap();
+    let charts: Vec<_> = header
+        .course
+        .iter()
+        .flatten()
+        .flat_map(|c| &c.charts)
+        .collect();
+    assert_eq!(charts.len(), 1);
+}
+
+#[test]
+fn test_lenient_mode_from_yaml() {
+    let wrong_type: BmsTableHeader =
+        serde_yaml::from_str("name: T\nsymbol: t\ndata_url: d.json\nmode: [7, 14]").unwrap();
+    assert!(wrong_type.mode.is_none());
+    let popn: BmsTableHeader =
+        serde_yaml::from_str("name: T\nsymbol: t\ndata_url: d.json\nmode: popn-9k").unwrap();
+    assert_eq!(popn.mode, Some(KeyMode::Popn9K));
+}
+
+#[test]
+fn test_table_round_trips_through_yaml_and_messagepack() {
+    let table = BmsTable {
+        header: serde_yaml::from_str(HEADER_YAML).unwrap(),
+        data: serde_yaml::from_str(DATA_YAML).unwrap(),
+    };
+
+    let yaml = serde_yaml::to_string(&table).unwrap();
+    assert_eq!(serde_yaml::from_str::<BmsTable>(&yaml).unwrap(), table);
+
+    let named = rmp_serde::to_vec_named(&table).unwrap();
+    assert_eq!(rmp_serde::from_slice::<BmsTable>(&named).unwrap(), table);
+
+    // Structs without flattened fields are written as arrays here.
+    let compact = rmp_serde::to_vec(&table).unwrap();
+    assert_eq!(rmp_serde::from_slice::<BmsTable>(&compact).unwrap(), table);
+}
+
+#[test]
+fn test_header_from_messagepack() {
+    let header_json = serde_json::json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{ "name": "Course", "sha256": ["e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"] }],
+        "level_order": [1, "!i"]
+    });
+    let bytes = rmp_serde::to_vec(&header_json).unwrap();
+    let header: BmsTableHeader = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(header.level_order, ["1", "!i"]);
+    let [chart] = header
+        .course
+        .iter()
+        .flatten()
+        .flat_map(|c| &c.charts)
+        .collect::<Vec<_>>()[..]
+    else {
+        panic!("expected one chart: {:?}", header.course);
+    };
+    assert!(chart.sha256.is_some());
+}
@@ -0,0 +1,184 @@
+//! Unit tests for validated chart hashes
+//!
+//! Checks hash normalization and validation, and how invalid hashes are handled by strict and lenient parsing.
+#![cfg(feature = "serde")]
+
+use bms_table::{
+    BmsTableData, BmsTableHeader, ChartItem, Md5, Sha256, de::ChartLocation, hash::ParseHashError,
+};
+use serde_json::json;
+
+const MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+const SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[test]
+fn test_hash_normalizes_case_and_whitespace() {
+    let md5: Md5 = format!("  {}\t", MD5.to_ascii_uppercase()).parse().unwrap();
+    assert_eq!(md5.to_string(), MD5);
+    assert_eq!(md5, MD5.parse().unwrap());
+    assert_eq!(md5.as_bytes().first(), Some(&0xd4));
+
+    let sha256: Sha256 = SHA256.to_ascii_uppercase().parse().unwrap();
+    assert_eq!(sha256.to_string(), SHA256);
+    assert_eq!(serde_json::to_value(sha256).unwrap(), json!(SHA256));
+}
+
+#[test]
+fn test_hash_rejects_invalid_values() {
+    assert_eq!(
+        SHA256.parse::<Md5>(),
+        Err(ParseHashError::Length {
+            expected: 32,
+            found: 64
+        })
+    );
+    assert_eq!(
+        MD5.parse::<Sha256>(),
+        Err(ParseHashError::Length {
+            expected: 64,
+            found: 32
+        })
+    );
+    assert_eq!(
+        "d41d8cd98f00b204e9800998ecf8427g".parse::<Md5>(),
+        Err(ParseHashError::Digit {
+            index: 31,
+            found: 'g'
+        })
+    );
+    assert!("".parse::<Md5>().is_err());
+}
+
+#[test]
+fn test_chart_item_hashes() {
+    let item: ChartItem = serde_json::from_value(json!({
+        "level": "1",
+        "md5": MD5.to_ascii_uppercase(),
+        "sha256": "  "
+    }))
+    .unwrap();
+    assert_eq!(item.md5, MD5.parse().ok());
+    assert_eq!(item.sha256, None);
+
+    let value = serde_json::to_value(&item).unwrap();
+    assert_eq!(value.get("md5"), Some(&json!(MD5)));
+
+    let invalid: ChartItem =
+        serde_json::from_value(json!({ "level": "1", "md5": "abc", "sha256": 1 })).unwrap();
+    assert_eq!((invalid.md5, invalid.sha256), (None, None));
+}
+
+#[test]
+fn test_data_lenient_reports_invalid_hash() {
+    let parsed = BmsTableData::from_json_value_lenient(json!([
+        { "level": "1", "md5": MD5 },
+        { "level": "2", "sha256": MD5 }
+    ]))
+    .unwrap();
+    let [_, kept] = parsed.value.charts.as_slice() else {
+        panic!("expected two charts: {:?}", parsed.value.charts);
+    };
+    assert_eq!((kept.level.as_str(), kept.sha256), ("2", None));
+    let [diagnostic] = parsed.diagnostics.as_slice() else {
+        panic!("expected one diagnostic: {:?}", parsed.diagnostics);
+    };
+    assert_eq!(diagnostic.index, 1);
+    assert_eq!(diagnostic.raw, json!({ "level": "2", "sha256": MD5 }));
+    assert!(
+        diagnostic.reason.contains("sha256") && diagnostic.reason.contains("64"),
+        "reason: {}",
+        diagnostic.reason
+    );
+
+    let strict: BmsTableData =
+        serde_json::from_value(json!([{ "level": "1", "md5": "abc" }, { "level": "2" }])).unwrap();
+    assert_eq!(strict.charts.len(), 2);
+}
+
+#[test]
+fn test_course_hash_lists() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{
+            "name": "Course",
+            "md5": [MD5, "not-a-hash"],
+            "sha256": [1, SHA256]
+        }]
+    });
+    let strict: BmsTableHeader = serde_json::from_value(header_json.clone()).unwrap();
+    let strict_course = strict.course.iter().flatten().next().unwrap();
+    assert_eq!(strict_course.charts.len(), 2);
+
+    let parsed = BmsTableHeader::from_json_value_lenient(header_json).unwrap();
+    let course = parsed.value.course.iter().flatten().next().unwrap();
+    let [from_md5, from_sha256] = course.charts.as_slice() else {
+        panic!("expected two charts: {:?}", course.charts);
+    };
+    assert_eq!(from_md5.md5, MD5.parse().ok());
+    assert_eq!(from_sha256.sha256, SHA256.parse().ok());
+
+    let [d0, d1] = parsed.diagnostics.as_slice() else {
+        panic!("expected two diagnostics: {:?}", parsed.diagnostics);
+    };
+    assert_eq!(
+        (d0.location, d0.index, &d0.raw),
+        (
+            ChartLocation::CourseMd5 {
+                group: 0,
+                course: 0
+            },
+            1,
+            &json!("not-a-hash")
+        )
+    );
+    assert_eq!(
+        (d1.location, d1.index, &d1.raw),
+        (
+            ChartLocation::CourseSha256 {
+                group: 0,
+                course: 0
+            },
+            0,
+            &json!(1)
+        )
+    );
+}
+
+#[test]
+fn test_course_chart_lenient_reports_invalid_hash() {
+    let parsed = BmsTableHeader::from_json_value_lenient(json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [{
+            "name": "Course",
+            "charts": [{ "title": "Bad hash", "md5": "xyz" }]
+        }]
+    }))
+    .unwrap();
+    let course = parsed.value.course.iter().flatten().next().unwrap();
+    let [chart] = course.charts.as_slice() else {
+        panic!("expected one chart: {:?}", course.charts);
+    };
+    assert_eq!(chart.md5, None);
+    let [diagnostic] = parsed.diagnostics.as_slice() else {
+        panic!("expected one diagnostic: {:?}", parsed.diagnostics);
+    };
+    assert_eq!(
+        (diagnostic.location, diagnostic.index),
+        (
+            ChartLocation::Course {
+                group: 0,
+                course: 0
+            },
+            0
+        )
+    );
+    assert!(
+        diagnostic.reason.contains("md5"),
+        "reason: {}",
+        diagnostic.reason
+    );
+}
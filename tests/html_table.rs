@@ -0,0 +1,156 @@
+//! Unit tests for scraping HTML-only tables
+//!
+//! Checks column mapping detection, md5 extraction from LR2IR links, the synthetic header, and HTML table lists.
+#![cfg(feature = "scraper")]
+
+use bms_table::{
+    Md5,
+    fetch::html_table::{ColumnMapping, ListColumnMapping, scrape_table, scrape_table_list},
+};
+use serde_json::json;
+
+/// A legacy table page with a header row, a nested table and a separator row.
+const LEGACY_PAGE: &str = r#"
+<html>
+<head><title> Legacy   Insane Table </title></head>
+<body>
+<table id="charts">
+  <tr><th>難易度</th><th>曲名</th><th>アーティスト</th><th>本体</th><th>差分</th><th>LR2IR</th></tr>
+  <tr>
+    <td>▼1</td><td>First <b>Song</b></td><td>Artist A</td>
+    <td><a href="https://example.com/first.zip">DL</a></td>
+    <td><a href="https://example.com/first_diff.zip">差分</a></td>
+    <td><a href="http://www.dream-pro.info/~lavalse/LR2IR/search.cgi?mode=ranking&amp;bmsmd5=D41D8CD98F00B204E9800998ECF8427E&amp;x=1">IR</a></td>
+  </tr>
+  <tr><td colspan="6"></td></tr>
+  <tr>
+    <td>▼12</td><td>Second</td><td>Artist B</td><td></td><td></td>
+    <td><a href="search.cgi?mode=ranking&bmsmd5=invalid">IR</a></td>
+  </tr>
+  <tr>
+    <td>▼1</td><td>Third</td><td><table><tr><td>Nested</td><td>Cell</td></tr></table></td><td></td><td></td><td></td>
+  </tr>
+</table>
+</body>
+</html>
+"#;
+
+#[test]
+fn test_detect_column_mapping() {
+    let mapping = ColumnMapping::detect(LEGACY_PAGE).unwrap();
+    assert_eq!(
+        mapping,
+        ColumnMapping {
+            table: "table".to_string(),
+            level: Some(0),
+            title: Some(1),
+            artist: Some(2),
+            lr2ir: Some(5),
+            url: Some(3),
+            url_diff: Some(4),
+        }
+    );
+    assert!(ColumnMapping::detect("<table><tr><td>1</td></tr></table>").is_none());
+}
+
+#[test]
+fn test_scrape_legacy_table() {
+    let mapping = ColumnMapping {
+        table: "#charts".to_string(),
+        ..ColumnMapping::detect(LEGACY_PAGE).unwrap()
+    };
+    let table = scrape_table(LEGACY_PAGE, &mapping).unwrap();
+    assert_eq!(table.header.name, "Legacy Insane Table");
+    assert_eq!(table.header.symbol, "▼");
+    assert_eq!(table.header.data_url, "");
+    assert_eq!(table.header.level_order, ["1", "12"]);
+
+    let [first, second, third] = table.data.charts.as_slice() else {
+        panic!("expected three charts: {:?}", table.data.charts);
+    };
+    assert_eq!(first.level, "1");
+    assert_eq!(first.title.as_deref(), Some("First Song"));
+    assert_eq!(first.artist.as_deref(), Some("Artist A"));
+    assert_eq!(
+        first.md5,
+        Some("d41d8cd98f00b204e9800998ecf8427e".parse::<Md5>().unwrap())
+    );
+    assert_eq!(first.url.as_deref(), Some("https://example.com/first.zip"));
+    assert_eq!(
+        first.url_diff.as_deref(),
+        Some("https://example.com/first_diff.zip")
+    );
+    // Invalid md5 values are dropped.
+    assert_eq!(second.level, "12");
+    assert_eq!(second.md5, None);
+    assert_eq!(second.url, None);
+    assert_eq!(third.artist.as_deref(), Some("Nested Cell"));
+}
+
+#[test]
+fn test_scrape_with_default_mapping() {
+    let html = r#"<table>
+        <tr><td>3</td><td>Song</td><td>Artist</td><td><a href="?bmsmd5=0cc175b9c0f1b6a831c399e269772661">IR</a></td></tr>
+        <tr><td>?</td><td>Other</td></tr>
+    </table>"#;
+    let table = scrape_table(html, &ColumnMapping::default()).unwrap();
+    assert_eq!(table.header.name, "");
+    assert_eq!(table.header.symbol, "");
+    assert_eq!(table.header.level_order, ["3", "?"]);
+    let [song, other] = table.data.charts.as_slice() else {
+        panic!("expected two charts: {:?}", table.data.charts);
+    };
+    assert!(song.md5.is_some());
+    assert_eq!(other.artist, None);
+
+    let missing = ColumnMapping {
+        table: "#missing".to_string(),
+        ..ColumnMapping::default()
+    };
+    assert!(scrape_table(html, &missing).is_none());
+}
+
+/// A table list page in the layout of the commonly shared `tablelist.html`.
+const LIST_PAGE: &str = r#"
+<table>
+  <thead><tr><th>記号</th><th>難易度表</th><th>タグ</th><th>コメント</th><th>更新日</th></tr></thead>
+  <tbody>
+    <tr><td>★</td><td><a href="https://example.com/insane/">Insane Table</a></td><td>発狂</td><td>Hard charts</td><td>2024-01-01</td></tr>
+    <tr><td>sl</td><td><a href="sl/table.html">Satellite</a></td><td></td><td></td><td></td></tr>
+    <tr><td>x</td><td>No link</td><td></td><td></td><td></td></tr>
+  </tbody>
+</table>
+"#;
+
+#[test]
+fn test_scrape_table_list() {
+    let mapping = ListColumnMapping::detect(LIST_PAGE).unwrap();
+    assert_eq!(mapping.name, Some(1));
+    assert_eq!(mapping.symbol, Some(0));
+    assert_eq!(mapping.url, None);
+    assert_eq!(
+        mapping.extra,
+        [
+            ("tag".to_string(), 2),
+            ("comment".to_string(), 3),
+            ("date".to_string(), 4)
+        ]
+    );
+
+    let base = url::Url::parse("https://example.org/list/tablelist.html").unwrap();
+    let list = scrape_table_list(LIST_PAGE, &base, &mapping).unwrap();
+    let [insane, satellite] = list.listes.as_slice() else {
+        panic!("expected two tables: {:?}", list.listes);
+    };
+    assert_eq!(insane.name, "Insane Table");
+    assert_eq!(insane.symbol, "★");
+    assert_eq!(insane.url.as_str(), "https://example.com/insane/");
+    assert_eq!(insane.extra.get("tag"), Some(&json!("発狂")));
+    assert_eq!(insane.extra.get("comment"), Some(&json!("Hard charts")));
+    assert_eq!(insane.extra.get("date"), Some(&json!("2024-01-01")));
+    assert_eq!(
+        satellite.url.as_str(),
+        "https://example.org/list/sl/table.html"
+    );
+    assert!(satellite.extra.is_empty());
+}
@@ -0,0 +1,87 @@
+//! Unit tests for level ordering and grouping by `level_order`
+#![cfg(feature = "serde")]
+
+use std::cmp::Ordering;
+
+use bms_table::{BmsTable, BmsTableData, BmsTableHeader, Level};
+use serde_json::json;
+
+fn make_table(level_order: serde_json::Value, levels: &[&str]) -> serde_json::Result<BmsTable> {
+    let header: BmsTableHeader = serde_json::from_value(json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "level_order": level_order
+    }))?;
+    let charts: Vec<_> = levels
+        .iter()
+        .enumerate()
+        .map(|(i, level)| json!({ "level": level, "title": format!("song {i}") }))
+        .collect();
+    let data: BmsTableData = serde_json::from_value(json!(charts))?;
+    Ok(BmsTable { header, data })
+}
+
+#[test]
+fn test_charts_by_level_follows_level_order() {
+    let table = make_table(
+        json!([0, 1, 2, "!i", "?"]),
+        &["?", "2", "0", "!i", "2", "1"],
+    )
+    .unwrap();
+
+    let groups = table.charts_by_level();
+    let names: Vec<&str> = groups.iter().map(|(level, _)| level.name()).collect();
+    assert_eq!(names, ["0", "1", "2", "!i", "?"]);
+
+    let (level2, charts2) = groups
+        .iter()
+        .find(|(level, _)| level.name() == "2")
+        .unwrap();
+    assert_eq!(level2.position(), Some(2));
+    let titles: Vec<_> = charts2.iter().map(|c| c.title.as_deref()).collect();
+    assert_eq!(titles, [Some("song 1"), Some("song 4")]);
+}
+
+#[test]
+fn test_unlisted_levels_go_last_numeric_first() {
+    let table = make_table(json!(["1", "2"]), &["x", "10", "2", "9", "1", "abc", "2.5"]).unwrap();
+
+    let names: Vec<&str> = table.levels().iter().map(Level::name).collect();
+    assert_eq!(names, ["1", "2", "2.5", "9", "10", "abc", "x"]);
+}
+
+#[test]
+fn test_empty_level_order_sorts_numerically() {
+    let table = make_table(json!([]), &["12", "3", "!i", "0"]).unwrap();
+
+    let names: Vec<&str> = table.levels().iter().map(Level::name).collect();
+    assert_eq!(names, ["0", "3", "12", "!i"]);
+}
+
+#[test]
+fn test_compare_levels_of_same_table() {
+    let table = make_table(json!([20, 10, 0]), &[]).unwrap();
+    let header = &table.header;
+
+    assert_eq!(header.compare_levels("20", "0"), Ordering::Less);
+    assert_eq!(header.compare_levels("0", "5"), Ordering::Less);
+    assert_eq!(header.compare_levels("10", "10"), Ordering::Equal);
+    assert!(header.level("10") > header.level("20"));
+    assert_eq!(header.level("5").as_number(), Some(5.0));
+    assert_eq!(header.level("5").position(), None);
+}
+
+#[test]
+fn test_sort_charts_by_level_is_stable() {
+    let mut table = make_table(json!(["b", "a"]), &["a", "b", "a", "b"]).unwrap();
+    table.sort_charts_by_level();
+
+    let titles: Vec<_> = table
+        .data
+        .charts
+        .iter()
+        .map(|c| c.title.as_deref().unwrap())
+        .collect();
+    assert_eq!(titles, ["song 1", "song 3", "song 0", "song 2"]);
+}
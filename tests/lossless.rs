@@ -0,0 +1,116 @@
+//! Unit tests for lossless round-trips
+//!
+//! Checks that headers and chart data serialize back to their original representation, and that edits are still written.
+#![cfg(feature = "serde")]
+
+use bms_table::{BmsTableData, BmsTableHeader, lossless::Lossless};
+use serde_json::{Value, json};
+
+const HEADER_JSON: &str = r#"{
+    "name": "Test Table",
+    "symbol": "t",
+    "data_url": "charts.json",
+    "mode": "BEAT-7K",
+    "last_update": 20240101,
+    "course": [
+        {
+            "name": "Course 1",
+            "constraint": ["grade_mirror", "gauge_lr2"],
+            "trophy": [{ "name": "goldmedal", "missrate": 2, "scorerate": 85.5 }],
+            "md5": ["D41D8CD98F00B204E9800998ECF8427E", "0cc175b9c0f1b6a831c399e269772661"]
+        },
+        {
+            "name": "Course 2",
+            "charts": [{ "title": "No level", "md5": "92eb5ffee6ae2fec3ad71c777531578f" }],
+            "sha256": ["e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"]
+        }
+    ],
+    "level_order": [0, 1, 2, "!i", 1.5],
+    "extra_field": { "b": 1, "a": [true, null] }
+}"#;
+
+const DATA_JSON: &str = r#"[
+    { "level": 12, "title": "Song", "md5": "", "id": 1 },
+    { "level": "!i", "title": "Other", "url": null, "sha256": "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855" },
+    { "title": "No level" }
+]"#;
+
+#[test]
+fn test_header_round_trip() {
+    let header = BmsTableHeader::from_json_str_lossless(HEADER_JSON).unwrap();
+    assert_eq!(header.value.level_order, ["0", "1", "2", "!i", "1.5"]);
+    assert_eq!(header.value.course.len(), 1);
+    assert_eq!(
+        serde_json::to_value(&header).unwrap(),
+        serde_json::from_str::<Value>(HEADER_JSON).unwrap()
+    );
+
+    // Without lossless parsing the representation is normalized.
+    let plain: BmsTableHeader = serde_json::from_str(HEADER_JSON).unwrap();
+    let normalized = serde_json::to_value(&plain).unwrap();
+    assert_eq!(normalized.pointer("/level_order/0"), Some(&json!("0")));
+    assert!(normalized.pointer("/course/0/0/md5").is_none());
+}
+
+#[test]
+fn test_data_round_trip() {
+    let data = BmsTableData::from_json_str_lossless(DATA_JSON).unwrap();
+    assert_eq!(data.value.charts.len(), 3);
+    assert_eq!(
+        serde_json::to_value(&data).unwrap(),
+        serde_json::from_str::<Value>(DATA_JSON).unwrap()
+    );
+
+    let text = serde_json::to_string(&data).unwrap();
+    let reparsed: Lossless<BmsTableData> = Lossless::from_json_str(&text).unwrap();
+    assert_eq!(reparsed.value, data.value);
+}
+
+#[test]
+fn test_edits_are_serialized() {
+    let mut header = BmsTableHeader::from_json_str_lossless(HEADER_JSON).unwrap();
+    let course = header.value.course.iter_mut().flatten().next().unwrap();
+    course.name = "Renamed".to_string();
+    header.value.name = "New Name".to_string();
+
+    let out = serde_json::to_value(&header).unwrap();
+    let original: Value = serde_json::from_str(HEADER_JSON).unwrap();
+    assert_eq!(out.get("name"), Some(&json!("New Name")));
+    // The edited course falls back to its normalized form, the other one keeps its sha256 list.
+    assert_eq!(out.pointer("/course/0/name"), Some(&json!("Renamed")));
+    assert_eq!(out.pointer("/course/0/charts/0/level"), Some(&json!("0")));
+    assert!(out.pointer("/course/0/md5").is_none());
+    assert_eq!(out.pointer("/course/1"), original.pointer("/course/1"));
+    assert_eq!(out.get("level_order"), Some(&json!([0, 1, 2, "!i", 1.5])));
+
+    let mut data = BmsTableData::from_json_str_lossless(DATA_JSON).unwrap();
+    data.value.charts.truncate(1);
+    let [chart] = data.value.charts.as_mut_slice() else {
+        panic!("expected one chart: {:?}", data.value.charts);
+    };
+    chart.title = Some("Edited".to_string());
+    assert_eq!(
+        serde_json::to_value(&data).unwrap(),
+        json!([{ "level": 12, "title": "Edited", "md5": "", "id": 1 }])
+    );
+}
+
+#[test]
+fn test_lossless_rejects_invalid_documents() {
+    assert!(BmsTableData::from_json_str_lossless(r#"[{"level": true}]"#).is_err());
+    assert!(BmsTableHeader::from_json_str_lossless("{}").is_err());
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn test_round_trip_preserves_key_order() {
+    let compact_header =
+        serde_json::to_string(&serde_json::from_str::<Value>(HEADER_JSON).unwrap()).unwrap();
+    let header = BmsTableHeader::from_json_str_lossless(&compact_header).unwrap();
+    assert_eq!(serde_json::to_string(&header).unwrap(), compact_header);
+
+    let compact_data =
+        serde_json::to_string(&serde_json::from_str::<Value>(DATA_JSON).unwrap()).unwrap();
+    let data = BmsTableData::from_json_str_lossless(&compact_data).unwrap();
+    assert_eq!(serde_json::to_string(&data).unwrap(), compact_data);
+}
@@ -1,10 +1,15 @@
 //! Unit tests for HTML parsing and bmstable URL extraction
 //!
-//! Verifies reading the `content` from `<meta name="bmstable">` and joining relative URLs.
+//! Verifies reading the `content` from `<meta name="bmstable">`, listing every header URL candidate, finding headers
+//! defined inline in scripts, and joining relative URLs.
 #![cfg(feature = "scraper")]
 
-use bms_table::fetch::{
-    HeaderQueryContent, get_web_header_json_value, try_extract_bmstable_from_html,
+use bms_table::{
+    BmsTableHeader,
+    fetch::{
+        CandidateOrigin, HeaderQueryContent, HeaderStrategy, extract_header_candidates,
+        extract_inline_table, get_web_header_json_value, try_extract_bmstable_from_html,
+    },
 };
 use url::Url;
 
@@ -72,3 +77,166 @@ fn test_get_web_header_json_value_parses_json_with_control_chars() {
         _ => panic!("should parse as JSON"),
     }
 }
+
+#[test]
+fn test_parser_no_bmstable_reports_header_not_found() {
+    let err = try_extract_bmstable_from_html("<html><head></head></html>").unwrap_err();
+    assert!(matches!(
+        err,
+        bms_table::Error::HeaderNotFound { url: None }
+    ));
+    assert!(!err.is_transient());
+}
+
+#[test]
+fn test_extract_header_candidates_in_priority_order() {
+    let html_content = r#"
+    <html>
+    <head>
+        <!-- moved from "old_header.json" -->
+        <link rel="BMSTABLE" href="link_rel.json">
+        <script src="js/header.json"></script>
+        <meta name="bmstable" content="header.json">
+    </head>
+    <body>
+        <a href="dl/sub_header.json">download</a>
+    </body>
+    </html>
+    "#;
+
+    let candidates = extract_header_candidates(html_content);
+    let found: Vec<_> = candidates
+        .iter()
+        .map(|c| (c.strategy, c.url.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (HeaderStrategy::MetaBmstable, "header.json"),
+            (HeaderStrategy::LinkRel, "link_rel.json"),
+            (HeaderStrategy::AnchorHref, "dl/sub_header.json"),
+            (HeaderStrategy::ScriptSrc, "js/header.json"),
+            (HeaderStrategy::MetaContent, "header.json"),
+            (HeaderStrategy::RawText, "old_header.json"),
+            (HeaderStrategy::RawText, "js/header.json"),
+            (HeaderStrategy::RawText, "header.json"),
+            (HeaderStrategy::RawText, "dl/sub_header.json"),
+        ]
+    );
+
+    let [meta, ..] = candidates.as_slice() else {
+        panic!("expected candidates");
+    };
+    let CandidateOrigin::Element(element) = &meta.origin else {
+        panic!("expected an element origin: {meta:?}");
+    };
+    assert!(element.starts_with("<meta"), "element: {element}");
+
+    let raw = candidates
+        .iter()
+        .find(|c| c.strategy == HeaderStrategy::RawText)
+        .unwrap();
+    let CandidateOrigin::Text(span) = &raw.origin else {
+        panic!("expected a text origin: {raw:?}");
+    };
+    assert_eq!(html_content.get(span.clone()), Some("old_header.json"));
+
+    assert_eq!(
+        try_extract_bmstable_from_html(html_content).unwrap(),
+        "header.json"
+    );
+}
+
+#[test]
+fn test_extract_header_candidates_empty() {
+    assert!(extract_header_candidates("<html><body>no table</body></html>").is_empty());
+}
+
+#[test]
+fn test_extract_inline_header_and_data() {
+    let html_content = r#"
+    <html>
+    <head><script src="jquery.js"></script></head>
+    <body>
+    <script>
+        // Loaded from "old_header.json" before
+        var config = { theme: 'dark' };
+        var mdata = [
+            { level: 1, title: 'It\'s "quoted"', md5: "d41d8cd98f00b204e9800998ecf8427e" },
+            { level: "2", title: `Template`, url: undefined, }, /* trailing comma */
+        ];
+        window.bmstable = {
+            name: 'Inline Table',
+            symbol: "i",
+            'data_url': "data.json",
+            level_order: [1, 2,],
+        };
+        if (window.bmstable == null) { render(mdata); }
+    </script>
+    </body>
+    </html>
+    "#;
+
+    let inline = extract_inline_table::<BmsTableHeader>(html_content).unwrap();
+    assert_eq!(inline.header.name, "Inline Table");
+    assert_eq!(inline.header.data_url, "data.json");
+    assert_eq!(inline.header.level_order, ["1", "2"]);
+    let header_raw: serde_json::Value = serde_json::from_str(&inline.header_raw).unwrap();
+    assert_eq!(header_raw.get("symbol"), Some(&serde_json::json!("i")));
+
+    let data = inline.data.unwrap();
+    let [first, second] = data.charts.as_slice() else {
+        panic!("expected two charts: {:?}", data.charts);
+    };
+    assert_eq!(first.title.as_deref(), Some("It's \"quoted\""));
+    assert_eq!(first.level, "1");
+    assert_eq!(second.title.as_deref(), Some("Template"));
+    assert_eq!(second.url, None);
+    assert!(inline.data_raw.is_some());
+
+    // The inline header wins over the raw text match in the comment.
+    assert!(matches!(
+        get_web_header_json_value::<BmsTableHeader>(html_content).unwrap(),
+        HeaderQueryContent::Inline(_)
+    ));
+}
+
+#[test]
+fn test_inline_header_without_data_url() {
+    let html_content = r#"<script>
+        var table = {
+            header: { name: "No Data URL", symbol: "n" },
+            data: [{ level: 1, title: "Song" }],
+        };
+    </script>"#;
+    let inline = extract_inline_table::<BmsTableHeader>(html_content).unwrap();
+    assert_eq!(inline.header.name, "No Data URL");
+    assert_eq!(inline.header.data_url, "");
+    let header_raw: BmsTableHeader = serde_json::from_str(&inline.header_raw).unwrap();
+    assert_eq!(header_raw, inline.header);
+    assert_eq!(inline.data.map(|data| data.charts.len()), Some(1));
+
+    // Without inline data, the header must say where its data is.
+    let header_only = r#"<script>var header = { name: "No Data URL", symbol: "n" };</script>"#;
+    assert!(extract_inline_table::<BmsTableHeader>(header_only).is_none());
+}
+
+#[test]
+fn test_inline_header_nested_and_declared_url_preferred() {
+    let nested = r#"<script>
+        const table = { header: { "name": "Nested", "symbol": "n", "data_url": "d.json" } };
+    </script>"#;
+    let inline = extract_inline_table::<BmsTableHeader>(nested).unwrap();
+    assert_eq!(inline.header.name, "Nested");
+    assert!(inline.data.is_none());
+    assert!(inline.data_raw.is_none());
+
+    let declared = format!(r#"<meta name="bmstable" content="header.json">{nested}"#);
+    match get_web_header_json_value::<BmsTableHeader>(&declared).unwrap() {
+        HeaderQueryContent::Url(url) => assert_eq!(url, "header.json"),
+        _ => panic!("a declared header URL is preferred"),
+    }
+
+    let not_a_header = r#"<script>var header = { title: "x" };</script>"#;
+    assert!(extract_inline_table::<BmsTableHeader>(not_a_header).is_none());
+}
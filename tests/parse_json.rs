@@ -3,7 +3,7 @@
 //! Covers common and edge inputs for headers, courses, and chart data to ensure deserialization and field compatibility behave correctly.
 #![cfg(feature = "serde")]
 
-use bms_table::{BmsTable, BmsTableData, BmsTableHeader, CourseInfo};
+use bms_table::{BmsTable, BmsTableData, BmsTableHeader, CourseInfo, KeyMode};
 use serde_json::json;
 use std::collections::BTreeMap;
 
@@ -27,7 +27,7 @@ fn test_build_bms_table_from_json() {
                             "scorerate": 90.0
                         }
                     ],
-                    "md5": ["test_md5_1", "test_md5_2"]
+                    "md5": ["9be32654beb27d6e17333366c0efa646", "65d88c79285ca4ab184bfc7615c3082d"]
                 }
             ]
         ],
@@ -39,8 +39,8 @@ fn test_build_bms_table_from_json() {
         {
             "level": "1",
             "id": 1,
-            "md5": "test_md5_1",
-            "sha256": "test_sha256_1",
+            "md5": "9be32654beb27d6e17333366c0efa646",
+            "sha256": "0b3636b633ed2f94be8d6f1bef6f8e14adc8b746dbf73694e00b88dbf9767ce1",
             "title": "Test Song",
             "artist": "Test Artist",
             "url": "https://example.com/test.bms",
@@ -89,8 +89,8 @@ fn test_build_bms_table_from_json() {
             course.charts
         );
     };
-    assert_eq!(c0.md5.as_deref(), Some("test_md5_1"));
-    assert_eq!(c1.md5.as_deref(), Some("test_md5_2"));
+    assert_eq!(c0.md5, "9be32654beb27d6e17333366c0efa646".parse().ok());
+    assert_eq!(c1.md5, "65d88c79285ca4ab184bfc7615c3082d".parse().ok());
 
     let [score] = bms_table.data.charts.as_slice() else {
         panic!(
@@ -100,8 +100,13 @@ fn test_build_bms_table_from_json() {
         );
     };
     assert_eq!(score.level, "1");
-    assert_eq!(score.md5, Some("test_md5_1".to_string()));
-    assert_eq!(score.sha256, Some("test_sha256_1".to_string()));
+    assert_eq!(score.md5, "9be32654beb27d6e17333366c0efa646".parse().ok());
+    assert_eq!(
+        score.sha256,
+        "0b3636b633ed2f94be8d6f1bef6f8e14adc8b746dbf73694e00b88dbf9767ce1"
+            .parse()
+            .ok()
+    );
     assert_eq!(score.title, Some("Test Song".to_string()));
     assert_eq!(score.artist, Some("Test Artist".to_string()));
     assert_eq!(score.url, Some("https://example.com/test.bms".to_string()));
@@ -172,9 +177,10 @@ fn test_build_bms_table_with_empty_fields() {
         );
     };
     assert_eq!(score.level, "1");
-    // Current behavior keeps empty strings as Some("") for optional string fields
-    assert_eq!(score.md5, Some("".to_string()));
-    assert_eq!(score.sha256, Some("".to_string()));
+    // Empty hashes are treated as missing
+    assert_eq!(score.md5, None);
+    assert_eq!(score.sha256, None);
+    // Other optional string fields keep empty strings as Some("")
     assert_eq!(score.title, Some("".to_string()));
     assert_eq!(score.artist, Some("".to_string()));
     assert_eq!(score.url, Some("".to_string()));
@@ -189,6 +195,12 @@ fn test_bms_table_creation() {
         data_url: "https://example.com/charts.json".to_string(),
         course: vec![],
         level_order: vec!["0".to_string(), "1".to_string()],
+        tag: None,
+        mode: None,
+        last_update: None,
+        update_url: None,
+        tag_order: None,
+        comment: None,
         extra: BTreeMap::new(),
     };
     let data = BmsTableData { charts: vec![] };
@@ -209,6 +221,12 @@ fn test_bms_table_partial_eq() {
         data_url: "https://example.com/charts.json".to_string(),
         course: vec![],
         level_order: vec!["0".to_string(), "1".to_string()],
+        tag: None,
+        mode: None,
+        last_update: None,
+        update_url: None,
+        tag_order: None,
+        comment: None,
         extra: BTreeMap::new(),
     };
     let data1 = BmsTableData { charts: vec![] };
@@ -234,8 +252,8 @@ fn test_chart_item_numeric_fields_to_string() {
             "level": 0,
             "id": 1,
             // Convert numeric fields to strings to match current deserialization behavior
-            "md5": "12345",
-            "sha256": "67890",
+            "md5": "1234567890abcdef1234567890abcdef",
+            "sha256": "1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
             "title": "987",
             "artist": "654",
             "url": "321",
@@ -251,8 +269,14 @@ fn test_chart_item_numeric_fields_to_string() {
         );
     };
     assert_eq!(score.level, "0");
-    assert_eq!(score.md5, Some("12345".to_string()));
-    assert_eq!(score.sha256, Some("67890".to_string()));
+    assert_eq!(
+        score.md5.map(|h| h.to_string()).as_deref(),
+        Some("1234567890abcdef1234567890abcdef")
+    );
+    assert_eq!(
+        score.sha256.map(|h| h.to_string()).as_deref(),
+        Some("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef")
+    );
     assert_eq!(score.title, Some("987".to_string()));
     assert_eq!(score.artist, Some("654".to_string()));
     assert_eq!(score.url, Some("321".to_string()));
@@ -296,7 +320,7 @@ fn test_bms_table_header_deserialize_vec_course_info() {
                         "scorerate": 70.0
                     }
                 ],
-                "md5": ["abc123", "def456"]
+                "md5": ["e99a18c428cb38d5f260853678922e03", "f26c2f431a8f57ae8013881556f8e279"]
             }
         ]
     }"#;
@@ -329,8 +353,8 @@ fn test_bms_table_header_deserialize_vec_course_info() {
             course.charts
         );
     };
-    assert_eq!(c0.md5.as_deref(), Some("abc123"));
-    assert_eq!(c1.md5.as_deref(), Some("def456"));
+    assert_eq!(c0.md5, "e99a18c428cb38d5f260853678922e03".parse().ok());
+    assert_eq!(c1.md5, "f26c2f431a8f57ae8013881556f8e279".parse().ok());
 }
 
 #[test]
@@ -351,7 +375,7 @@ fn test_bms_table_header_deserialize_vec_vec_course_info() {
                             "scorerate": 70.0
                         }
                     ],
-                    "md5": ["abc123", "def456"]
+                    "md5": ["e99a18c428cb38d5f260853678922e03", "f26c2f431a8f57ae8013881556f8e279"]
                 }
             ],
             [
@@ -365,7 +389,7 @@ fn test_bms_table_header_deserialize_vec_vec_course_info() {
                             "scorerate": 60.0
                         }
                     ],
-                    "md5": ["ghi789"]
+                    "md5": ["8195bde6f76982023b6a4638e3930230"]
                 }
             ]
         ]
@@ -410,8 +434,8 @@ fn test_bms_table_header_deserialize_vec_vec_course_info() {
             course1.charts
         );
     };
-    assert_eq!(c10.md5.as_deref(), Some("abc123"));
-    assert_eq!(c11.md5.as_deref(), Some("def456"));
+    assert_eq!(c10.md5, "e99a18c428cb38d5f260853678922e03".parse().ok());
+    assert_eq!(c11.md5, "f26c2f431a8f57ae8013881556f8e279".parse().ok());
 
     assert_eq!(course2.charts.len(), 1);
     let [c20] = course2.charts.as_slice() else {
@@ -421,7 +445,7 @@ fn test_bms_table_header_deserialize_vec_vec_course_info() {
             course2.charts
         );
     };
-    assert_eq!(c20.md5.as_deref(), Some("ghi789"));
+    assert_eq!(c20.md5, "8195bde6f76982023b6a4638e3930230".parse().ok());
 }
 
 #[test]
@@ -488,7 +512,7 @@ fn test_course_info_deserialize_sha256list_to_charts() {
                 "scorerate": 70.0
             }
         ],
-        "sha256": ["sha256_hash_1", "sha256_hash_2"]
+        "sha256": ["b2e13c9e45f35e5940b424beb130d2e8102c13e7f3667548db31c2a86e83a100", "f047cb835377ee9ad0cdef700f0d37b88cd0801c5cfeb93ab327c837a0d691ca"]
     }"#;
 
     let result: serde_json::Result<CourseInfo> = serde_json::from_str(json_data);
@@ -507,10 +531,20 @@ fn test_course_info_deserialize_sha256list_to_charts() {
             course_info.charts
         );
     };
-    assert_eq!(c0.sha256.as_deref(), Some("sha256_hash_1"));
-    assert_eq!(c1.sha256.as_deref(), Some("sha256_hash_2"));
-    assert_eq!(c0.md5.as_deref(), None);
-    assert_eq!(c1.md5.as_deref(), None);
+    assert_eq!(
+        c0.sha256,
+        "b2e13c9e45f35e5940b424beb130d2e8102c13e7f3667548db31c2a86e83a100"
+            .parse()
+            .ok()
+    );
+    assert_eq!(
+        c1.sha256,
+        "f047cb835377ee9ad0cdef700f0d37b88cd0801c5cfeb93ab327c837a0d691ca"
+            .parse()
+            .ok()
+    );
+    assert_eq!(c0.md5, None);
+    assert_eq!(c1.md5, None);
 }
 
 #[test]
@@ -525,8 +559,8 @@ fn test_course_info_deserialize_md5_and_sha256_to_charts() {
                 "scorerate": 70.0
             }
         ],
-        "md5": ["md5_hash_1"],
-        "sha256": ["sha256_hash_1"],
+        "md5": ["abbcea3a589629a7cc241efd0abe77a9"],
+        "sha256": ["b2e13c9e45f35e5940b424beb130d2e8102c13e7f3667548db31c2a86e83a100"],
         "charts": [
             {
                 "level": "2",
@@ -556,10 +590,18 @@ fn test_course_info_deserialize_md5_and_sha256_to_charts() {
     assert_eq!(existing.title.as_deref(), Some("Existing Chart"));
     assert_eq!(existing.artist.as_deref(), Some("Test Artist"));
 
-    assert_eq!(from_md5.md5.as_deref(), Some("md5_hash_1"));
+    assert_eq!(
+        from_md5.md5,
+        "abbcea3a589629a7cc241efd0abe77a9".parse().ok()
+    );
     assert_eq!(from_md5.level.as_str(), "0");
 
-    assert_eq!(from_sha256.sha256.as_deref(), Some("sha256_hash_1"));
+    assert_eq!(
+        from_sha256.sha256,
+        "b2e13c9e45f35e5940b424beb130d2e8102c13e7f3667548db31c2a86e83a100"
+            .parse()
+            .ok()
+    );
     assert_eq!(from_sha256.level.as_str(), "0");
 }
 
@@ -572,6 +614,12 @@ fn test_json_serialization() {
         data_url: "charts.json".to_string(),
         course: vec![],
         level_order: vec!["0".to_string(), "1".to_string(), "!i".to_string()],
+        tag: None,
+        mode: None,
+        last_update: None,
+        update_url: None,
+        tag_order: None,
+        comment: None,
         extra: BTreeMap::new(),
     };
 
@@ -582,3 +630,90 @@ fn test_json_serialization() {
     expected.course = vec![Vec::new()];
     assert_eq!(expected, parsed);
 }
+
+#[test]
+fn test_header_promotes_well_known_fields() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "dp",
+        "data_url": "charts.json",
+        "tag": "dp",
+        "mode": "beat-14k",
+        "last_update": 20240101,
+        "update_url": "https://example.com/table.html",
+        "unknown_hint": { "nested": true }
+    });
+    let header: BmsTableHeader = serde_json::from_value(header_json).unwrap();
+    assert_eq!(header.tag.as_deref(), Some("dp"));
+    assert_eq!(header.mode, Some(KeyMode::Beat14K));
+    assert!(header.mode.as_ref().is_some_and(KeyMode::is_double));
+    assert_eq!(header.last_update.as_deref(), Some("20240101"));
+    assert_eq!(
+        header.update_url.as_deref(),
+        Some("https://example.com/table.html")
+    );
+    assert!(!header.extra.contains_key("tag"));
+    assert!(!header.extra.contains_key("mode"));
+    assert_eq!(
+        header.extra.get("unknown_hint"),
+        Some(&json!({ "nested": true }))
+    );
+
+    let value = serde_json::to_value(&header).unwrap();
+    assert_eq!(value.get("mode"), Some(&json!("beat-14k")));
+    assert_eq!(value.get("tag"), Some(&json!("dp")));
+}
+
+#[test]
+fn test_header_unknown_mode_round_trips() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "k",
+        "data_url": "charts.json",
+        "mode": "keyboard-48k"
+    });
+    let header: BmsTableHeader = serde_json::from_value(header_json).unwrap();
+    assert_eq!(
+        header.mode,
+        Some(KeyMode::Unknown("keyboard-48k".to_string()))
+    );
+    assert!(header.tag.is_none());
+
+    let value = serde_json::to_value(&header).unwrap();
+    assert_eq!(value.get("mode"), Some(&json!("keyboard-48k")));
+    assert!(value.get("tag").is_none());
+    assert!(value.get("last_update").is_none());
+}
+
+#[test]
+fn test_header_mode_keeps_original_spelling() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "sl",
+        "data_url": "charts.json",
+        "mode": "BEAT-7K"
+    });
+    let header: BmsTableHeader = serde_json::from_value(header_json).unwrap();
+    assert_eq!(header.mode, Some(KeyMode::Unknown("BEAT-7K".to_string())));
+
+    let value = serde_json::to_value(&header).unwrap();
+    assert_eq!(value.get("mode"), Some(&json!("BEAT-7K")));
+}
+
+#[test]
+fn test_header_lenient_mode_and_hints() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "sl",
+        "data_url": "charts.json",
+        "mode": 7,
+        "tag_order": 1,
+        "comment": "Updated weekly"
+    });
+    let header: BmsTableHeader = serde_json::from_value(header_json).unwrap();
+    assert!(header.mode.is_none());
+    assert_eq!(header.tag_order.as_deref(), Some("1"));
+    assert_eq!(header.comment.as_deref(), Some("Updated weekly"));
+    assert!(!header.extra.contains_key("tag_order"));
+    assert!(!header.extra.contains_key("comment"));
+}
@@ -0,0 +1,151 @@
+//! Unit tests for lenient chart parsing
+//!
+//! Checks that malformed chart entries are skipped and reported with their index, raw value and reason.
+#![cfg(feature = "serde")]
+
+use bms_table::{
+    BmsTableData, BmsTableHeader,
+    de::{ChartDiagnostic, ChartLocation},
+};
+use serde_json::json;
+
+#[test]
+fn test_data_lenient_skips_invalid_entries() {
+    let data_json = json!([
+        { "level": "1", "title": "Valid 1" },
+        null,
+        { "level": true, "title": "Bad level" },
+        ["not", "an", "object"],
+        { "level": 2, "title": "Valid 2" }
+    ]);
+
+    let parsed = BmsTableData::from_json_value_lenient(data_json).unwrap();
+    let [c0, c1] = parsed.value.charts.as_slice() else {
+        panic!(
+            "expected two charts, got {}: {:?}",
+            parsed.value.charts.len(),
+            parsed.value.charts
+        );
+    };
+    assert_eq!(c0.title.as_deref(), Some("Valid 1"));
+    assert_eq!(c1.level, "2");
+
+    let [d0, d1, d2] = parsed.diagnostics.as_slice() else {
+        panic!(
+            "expected three diagnostics, got {}: {:?}",
+            parsed.diagnostics.len(),
+            parsed.diagnostics
+        );
+    };
+    assert_eq!(d0.location, ChartLocation::Data);
+    assert_eq!((d0.index, &d0.raw), (1, &json!(null)));
+    assert_eq!(d1.index, 2);
+    assert_eq!(d1.raw, json!({ "level": true, "title": "Bad level" }));
+    assert!(d1.reason.contains("true"), "reason: {}", d1.reason);
+    assert_eq!(d2.index, 3);
+    assert!(d2.raw.is_array());
+}
+
+#[test]
+fn test_data_lenient_rejects_non_array() {
+    assert!(BmsTableData::from_json_str_lenient(r#"{"charts": 1}"#).is_err());
+    assert!(BmsTableData::from_json_str_lenient("[").is_err());
+}
+
+#[test]
+fn test_data_strict_still_fails_on_invalid_entry() {
+    let data_json = json!([{ "level": "1" }, null]);
+    assert!(serde_json::from_value::<BmsTableData>(data_json).is_err());
+}
+
+#[test]
+fn test_header_lenient_skips_invalid_course_charts() {
+    let header_json = r#"{
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [
+            [
+                {
+                    "name": "Course 1",
+                    "charts": [
+                        { "title": "No level" },
+                        null,
+                        { "level": "3", "title": "Has level" }
+                    ]
+                }
+            ],
+            [
+                {
+                    "name": "Course 2",
+                    "charts": [[1, 2]],
+                    "md5": ["0123456789abcdef0123456789abcdef"]
+                }
+            ]
+        ]
+    }"#;
+
+    let parsed = BmsTableHeader::from_json_str_lenient(header_json).unwrap();
+    let [group1, group2] = parsed.value.course.as_slice() else {
+        panic!("expected two groups: {:?}", parsed.value.course);
+    };
+    let [course1] = group1.as_slice() else {
+        panic!("expected one course: {group1:?}");
+    };
+    let [course2] = group2.as_slice() else {
+        panic!("expected one course: {group2:?}");
+    };
+    let [first, second] = course1.charts.as_slice() else {
+        panic!("expected two charts: {:?}", course1.charts);
+    };
+    assert_eq!(first.level, "0");
+    assert_eq!(second.level, "3");
+    assert_eq!(course2.charts.len(), 1);
+
+    let [d0, d1]: &[ChartDiagnostic] = parsed.diagnostics.as_slice() else {
+        panic!("expected two diagnostics: {:?}", parsed.diagnostics);
+    };
+    assert_eq!(
+        d0.location,
+        ChartLocation::Course {
+            group: 0,
+            course: 0
+        }
+    );
+    assert_eq!(d0.index, 1);
+    assert_eq!(
+        d1.location,
+        ChartLocation::Course {
+            group: 1,
+            course: 0
+        }
+    );
+    assert_eq!(d1.index, 0);
+    assert_eq!(d1.raw, json!([1, 2]));
+}
+
+#[test]
+fn test_header_lenient_flat_course_location() {
+    let header_json = json!({
+        "name": "Test Table",
+        "symbol": "t",
+        "data_url": "charts.json",
+        "course": [
+            { "name": "A", "charts": [] },
+            { "name": "B", "charts": [{ "level": {} }] }
+        ]
+    });
+
+    let parsed = BmsTableHeader::from_json_value_lenient(header_json).unwrap();
+    let [diagnostic] = parsed.diagnostics.as_slice() else {
+        panic!("expected one diagnostic: {:?}", parsed.diagnostics);
+    };
+    assert_eq!(
+        diagnostic.location,
+        ChartLocation::Course {
+            group: 0,
+            course: 1
+        }
+    );
+    assert_eq!(diagnostic.index, 0);
+}
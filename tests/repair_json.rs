@@ -0,0 +1,92 @@
+//! Unit tests for repairing JSON-like table files
+//!
+//! Checks JSONP callbacks, variable assignments, default exports, comments and trailing commas, and the repairs reported for each.
+#![cfg(feature = "scraper")]
+
+use bms_table::{
+    BmsTableData,
+    fetch::{JsonRepair, parse_json_str_with_fallback, parse_json_str_with_repairs},
+};
+
+#[test]
+fn test_valid_json_needs_no_repair() {
+    let repaired = parse_json_str_with_repairs::<BmsTableData>(r#"[{"level": "1"}]"#).unwrap();
+    assert_eq!(repaired.value.charts.len(), 1);
+    assert_eq!(repaired.json, r#"[{"level": "1"}]"#);
+    assert!(repaired.repairs.is_empty());
+
+    let cleaned = parse_json_str_with_repairs::<BmsTableData>("\u{0}[]\u{c}").unwrap();
+    assert_eq!(cleaned.repairs, [JsonRepair::ControlChars]);
+}
+
+#[test]
+fn test_jsonp_callback() {
+    let raw =
+        "// generated file\nbmsdata.callback([\n  {\"level\": \"1\", \"title\": \"Song\"},\n]);\n";
+    let repaired = parse_json_str_with_repairs::<BmsTableData>(raw).unwrap();
+    assert_eq!(repaired.value.charts.len(), 1);
+    assert_eq!(
+        repaired.repairs,
+        [
+            JsonRepair::Callback,
+            JsonRepair::TrailingSemicolon,
+            JsonRepair::TrailingCommas,
+            JsonRepair::Comments,
+        ]
+    );
+    let reparsed: BmsTableData = serde_json::from_str(&repaired.json).unwrap();
+    assert_eq!(reparsed, repaired.value);
+}
+
+#[test]
+fn test_variable_assignment_and_export() {
+    let raw =
+        r#"var mdata = [{ level: 2, title: 'It\'s', md5: "" }, /* removed */ { "level": "3" }];"#;
+    let repaired = parse_json_str_with_repairs::<BmsTableData>(raw).unwrap();
+    let [first, second] = repaired.value.charts.as_slice() else {
+        panic!("expected two charts: {:?}", repaired.value.charts);
+    };
+    assert_eq!(first.level, "2");
+    assert_eq!(first.title.as_deref(), Some("It's"));
+    assert_eq!(second.level, "3");
+    assert_eq!(
+        repaired.repairs,
+        [
+            JsonRepair::Assignment,
+            JsonRepair::TrailingSemicolon,
+            JsonRepair::Comments,
+            JsonRepair::JsSyntax,
+        ]
+    );
+
+    let exported =
+        parse_json_str_with_repairs::<BmsTableData>("export default [\n{\"level\": \"1\"}\n]\n")
+            .unwrap();
+    assert_eq!(exported.repairs, [JsonRepair::Assignment]);
+
+    let (data, json) = parse_json_str_with_fallback::<BmsTableData>("window.data = [];").unwrap();
+    assert!(data.charts.is_empty());
+    assert_eq!(json, "[]");
+}
+
+#[test]
+fn test_unrepairable_inputs() {
+    for raw in [
+        "<html><script>var x = [];</script></html>",
+        "callback([]",
+        "f([]); g();",
+        "1 + [] ",
+        "var x = [{\"level\": true}];",
+    ] {
+        assert!(
+            parse_json_str_with_repairs::<BmsTableData>(raw).is_err(),
+            "{raw:?} must not parse"
+        );
+    }
+
+    let Err(err) = parse_json_str_with_fallback::<BmsTableData>("callback([]") else {
+        panic!("an unterminated callback must not parse");
+    };
+    assert!(matches!(err, bms_table::Error::Json { url: None, .. }));
+    assert!(!err.is_transient());
+}
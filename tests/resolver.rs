@@ -0,0 +1,315 @@
+//! Unit tests for the IO-free table resolver
+//!
+//! Drives [`TableResolver`] with recorded responses, checking the requests it makes, candidate fallback,
+//! redirects and error reporting without any network access.
+#![cfg(feature = "scraper")]
+
+use bms_table::{
+    Error,
+    error::FetchStage,
+    fetch::{
+        FetchedTable, HttpRequest, HttpResponse, ResolveStep, TableResolver, TableUpdate,
+        TableValidators,
+    },
+};
+use url::Url;
+
+/// Header JSON used by the fixtures.
+const HEADER_JSON: &str = r#"{"name": "Test", "symbol": "t", "data_url": "data.json"}"#;
+
+/// Chart data JSON used by the fixtures.
+const DATA_JSON: &str = r#"[{"level": "1", "title": "Song"}]"#;
+
+/// Drive a resolver with `respond`, recording every requested URL.
+fn drive(
+    url: Url,
+    respond: impl Fn(&Url) -> Result<HttpResponse, Error>,
+) -> (Vec<Url>, Result<FetchedTable, Error>) {
+    let (requests, result) = drive_with(url, TableValidators::default(), |request| {
+        respond(&request.url)
+    });
+    let urls = requests.into_iter().map(|request| request.url).collect();
+    let result = result.map(|update| match update {
+        TableUpdate::Modified(fetched) => *fetched,
+        TableUpdate::NotModified => panic!("no validators were given"),
+    });
+    (urls, result)
+}
+
+/// Drive a resolver started from `validators` with `respond`, recording every request.
+fn drive_with(
+    url: Url,
+    validators: TableValidators,
+    respond: impl Fn(&HttpRequest) -> Result<HttpResponse, Error>,
+) -> (Vec<HttpRequest>, Result<TableUpdate, Error>) {
+    let mut requests = Vec::new();
+    let mut resolver = TableResolver::with_validators(url, validators);
+    loop {
+        let request = resolver.request().clone();
+        let step = match respond(&request) {
+            Ok(response) => resolver.respond(response),
+            Err(error) => resolver.fail(error),
+        };
+        requests.push(request);
+        match step {
+            Ok(ResolveStep::Fetch(next)) => resolver = next,
+            Ok(ResolveStep::Done(fetched)) => {
+                return (requests, Ok(TableUpdate::Modified(fetched)));
+            }
+            Ok(ResolveStep::NotModified) => return (requests, Ok(TableUpdate::NotModified)),
+            Err(error) => return (requests, Err(error)),
+        }
+    }
+}
+
+/// A successful response with `body`.
+fn ok(url: &Url, body: &str) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::new(
+        url.clone(),
+        200,
+        body.as_bytes().to_vec(),
+    ))
+}
+
+#[test]
+fn test_resolve_page_header_and_data() {
+    let (requested, result) =
+        drive(
+            Url::parse("https://example.com/table/").unwrap(),
+            |url| match url.path() {
+                "/table/" => ok(url, r#"<meta name="bmstable" content="header.json">"#),
+                "/table/header.json" => ok(url, HEADER_JSON),
+                _ => ok(url, DATA_JSON),
+            },
+        );
+    let fetched = result.unwrap();
+    assert_eq!(fetched.table.header.name, "Test");
+    assert_eq!(fetched.table.data.charts.len(), 1);
+    assert_eq!(fetched.raw.data_raw, DATA_JSON);
+    assert_eq!(
+        requested.iter().map(url::Url::path).collect::<Vec<_>>(),
+        ["/table/", "/table/header.json", "/table/data.json"]
+    );
+}
+
+#[test]
+fn test_resolve_header_candidates_fall_back() {
+    let page = r#"<meta name="bmstable" content="missing.json">
+        <a href="offline_header.json">x</a><a href="header.json">y</a>"#;
+    let (requested, result) = drive(Url::parse("https://example.com/").unwrap(), |url| match url
+        .path()
+    {
+        "/" => ok(url, page),
+        "/missing.json" => Ok(HttpResponse::new(url.clone(), 404, Vec::new())),
+        "/offline_header.json" => Err(Error::Network {
+            stage: FetchStage::Header,
+            url: url.clone(),
+            transient: true,
+            source: "connection refused".into(),
+        }),
+        "/header.json" => ok(url, HEADER_JSON),
+        _ => ok(url, DATA_JSON),
+    });
+    let fetched = result.unwrap();
+    assert_eq!(requested.len(), 5);
+    let [missing, offline] = fetched.header_attempts.as_slice() else {
+        panic!("expected two attempts: {:?}", fetched.header_attempts);
+    };
+    assert!(matches!(missing.error, Error::Status { status: 404, .. }));
+    assert!(offline.error.is_transient());
+    assert_eq!(
+        fetched.raw.header_json_url.as_str(),
+        "https://example.com/header.json"
+    );
+}
+
+#[test]
+fn test_resolve_against_redirected_url() {
+    let (requested, result) = drive(
+        Url::parse("https://example.com/old").unwrap(),
+        |url| match url.path() {
+            "/old" => Ok(HttpResponse::new(
+                Url::parse("https://example.com/new/").unwrap(),
+                200,
+                br#"<meta name="bmstable" content="header.json">"#.to_vec(),
+            )
+            .with_content_type("text/html; charset=utf-8")),
+            "/new/header.json" => ok(url, HEADER_JSON),
+            "/new/data.json" => ok(url, DATA_JSON),
+            _ => Ok(HttpResponse::new(url.clone(), 404, Vec::new())),
+        },
+    );
+    let fetched = result.unwrap();
+    assert_eq!(
+        requested.last().map(Url::as_str),
+        Some("https://example.com/new/data.json")
+    );
+    assert_eq!(fetched.raw.header_encoding, "UTF-8");
+}
+
+#[test]
+fn test_resolve_inline_table_needs_one_request() {
+    let page = r#"<script>
+        var header = { name: "Inline", symbol: "i", data_url: "unused.json" };
+        var data = [{ level: 1, title: "Song" }];
+    </script>"#;
+    let (requested, result) = drive(Url::parse("https://example.com/").unwrap(), |url| {
+        ok(url, page)
+    });
+    let fetched = result.unwrap();
+    assert_eq!(requested.len(), 1);
+    assert_eq!(fetched.table.header.name, "Inline");
+    assert_eq!(fetched.table.data.charts.len(), 1);
+}
+
+#[test]
+fn test_resolve_errors_end_the_resolution() {
+    let (_, page_result) = drive(Url::parse("https://example.com/").unwrap(), |url| {
+        Ok(HttpResponse::new(url.clone(), 503, Vec::new()))
+    });
+    let Err(page_error) = page_result else {
+        panic!("a failed web page must end the resolution");
+    };
+    assert!(matches!(
+        page_error,
+        Error::Status {
+            stage: FetchStage::WebPage,
+            status: 503,
+            ..
+        }
+    ));
+    assert!(page_error.is_transient());
+
+    let (requested, data_result) = drive(
+        Url::parse("https://example.com/header.json").unwrap(),
+        |url| match url.path() {
+            "/header.json" => ok(url, HEADER_JSON),
+            _ => ok(url, "<html>not data</html>"),
+        },
+    );
+    assert_eq!(requested.len(), 2);
+    assert!(matches!(data_result, Err(Error::DataJson { .. })));
+}
+
+/// Serve the page, header and data of a table with the given bodies, tagging each with an `ETag` of its body
+/// and answering `304 Not Modified` when the request sends that tag back.
+fn serve_tagged(request: &HttpRequest, page: &str, header: &str, data: &str) -> HttpResponse {
+    let body = match request.url.path() {
+        "/" => page,
+        "/header.json" => header,
+        _ => data,
+    };
+    let etag = format!("\"{}\"", body.len());
+    if request.if_none_match.as_deref() == Some(etag.as_str()) {
+        return HttpResponse::new(request.url.clone(), 304, Vec::new());
+    }
+    HttpResponse::new(request.url.clone(), 200, body.as_bytes().to_vec()).with_etag(etag)
+}
+
+#[test]
+fn test_resolve_not_modified() {
+    let url = Url::parse("https://example.com/").unwrap();
+    let page = r#"<meta name="bmstable" content="header.json">"#;
+    let (_, first) = drive_with(url.clone(), TableValidators::default(), |request| {
+        Ok(serve_tagged(request, page, HEADER_JSON, DATA_JSON))
+    });
+    let Ok(TableUpdate::Modified(fetched)) = first else {
+        panic!("the first fetch must read the table");
+    };
+    let validators = fetched.validators;
+    assert_eq!(
+        validators.data.as_ref().and_then(|v| v.etag.clone()),
+        Some(format!("\"{}\"", DATA_JSON.len()))
+    );
+
+    // Unchanged page, header and data: every request is conditional, and nothing is read.
+    let (requests, unchanged) = drive_with(url.clone(), validators.clone(), |request| {
+        Ok(serve_tagged(request, page, HEADER_JSON, DATA_JSON))
+    });
+    assert!(matches!(unchanged, Ok(TableUpdate::NotModified)));
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(HttpRequest::is_conditional));
+
+    // Changed data under an unchanged header: the header is read again without validators.
+    let new_data = r#"[{"level": "1", "title": "Song"}, {"level": "2", "title": "New"}]"#;
+    let (refetches, changed) = drive_with(url, validators, |request| {
+        Ok(serve_tagged(request, page, HEADER_JSON, new_data))
+    });
+    let Ok(TableUpdate::Modified(updated)) = changed else {
+        panic!("changed data must be read");
+    };
+    assert_eq!(updated.table.header.name, "Test");
+    assert_eq!(updated.table.data.charts.len(), 2);
+    let [.., refetched] = refetches.as_slice() else {
+        panic!("expected requests");
+    };
+    assert_eq!(refetched.url.path(), "/header.json");
+    assert!(!refetched.is_conditional());
+}
+
+#[test]
+fn test_resolve_not_modified_inline_table() {
+    let url = Url::parse("https://example.com/").unwrap();
+    let page = r#"<script>
+        var header = { name: "Inline", symbol: "i", data_url: "unused.json" };
+        var data = [{ level: 1, title: "Song" }];
+    </script>"#;
+    let (_, first) = drive_with(url.clone(), TableValidators::default(), |request| {
+        Ok(serve_tagged(request, page, "", ""))
+    });
+    let Ok(TableUpdate::Modified(fetched)) = first else {
+        panic!("the first fetch must read the table");
+    };
+    assert_eq!(fetched.validators.data, fetched.validators.web_page);
+
+    let (requests, unchanged) = drive_with(url, fetched.validators, |request| {
+        Ok(serve_tagged(request, page, "", ""))
+    });
+    assert!(matches!(unchanged, Ok(TableUpdate::NotModified)));
+    assert_eq!(requests.len(), 1);
+}
+
+#[test]
+fn test_resolve_changed_data_of_inline_header() {
+    let url = Url::parse("https://example.com/t/").unwrap();
+    let page =
+        r#"<script>var header = { name: "Inline", symbol: "i", data_url: "data.json" };</script>"#;
+    // The page lives under `/t/`, so pick its body by path rather than through `serve_tagged`'s layout.
+    let serve = |data: &'static str| {
+        move |request: &HttpRequest| {
+            let body = if request.url.path() == "/t/" {
+                page
+            } else {
+                data
+            };
+            Ok(serve_tagged(request, body, "", body))
+        }
+    };
+    let (_, first) = drive_with(url.clone(), TableValidators::default(), serve(DATA_JSON));
+    let Ok(TableUpdate::Modified(fetched)) = first else {
+        panic!("the first fetch must read the table");
+    };
+    assert_eq!(fetched.validators.header, fetched.validators.web_page);
+
+    // Unchanged page, changed data: the page is resolved again instead of being parsed as a header.
+    let new_data = r#"[{"level": "1", "title": "Song"}, {"level": "2", "title": "New"}]"#;
+    let (requests, changed) = drive_with(url.clone(), fetched.validators, serve(new_data));
+    let updated = match changed {
+        Ok(TableUpdate::Modified(updated)) => updated,
+        Ok(TableUpdate::NotModified) => panic!("changed data must be read"),
+        Err(error) => panic!("changed data must be read: {error}"),
+    };
+    assert_eq!(updated.table.header.name, "Inline");
+    assert_eq!(updated.table.data.charts.len(), 2);
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.stage != FetchStage::Header)
+    );
+    let [.., page_again, data_again] = requests.as_slice() else {
+        panic!("expected requests");
+    };
+    assert_eq!(page_again.url, url);
+    assert!(!page_again.is_conditional());
+    assert_eq!(data_again.url.path(), "/t/data.json");
+}
@@ -0,0 +1,86 @@
+//! Unit tests for trophy evaluation
+//!
+//! Checks the miss rate and score rate math and which course trophies a play achieves.
+
+use bms_table::{CourseInfo, JudgeCounts, Trophy};
+
+/// Build a trophy with the given requirements.
+fn trophy(name: &str, missrate: f64, scorerate: f64) -> Trophy {
+    Trophy {
+        name: name.to_string(),
+        missrate,
+        scorerate,
+    }
+}
+
+#[test]
+fn test_rates_match_beatoraja() {
+    let play = JudgeCounts {
+        pgreat: 1500,
+        great: 300,
+        good: 100,
+        bad: 20,
+        poor: 30,
+        empty_poor: 10,
+        total_notes: 2000,
+    };
+    assert_eq!(play.exscore(), 3300);
+    // 20 BAD + 30 POOR + 10 empty POOR + 50 unjudged notes
+    assert_eq!(play.misses(), 110);
+    assert!((play.score_rate() - 82.5).abs() < 1e-9);
+    assert!((play.miss_rate() - 5.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_zero_notes_rates() {
+    let play = JudgeCounts::default();
+    assert_eq!(play.exscore(), 0);
+    assert_eq!(play.misses(), 0);
+    assert!(play.score_rate().abs() < f64::EPSILON);
+    assert!(play.miss_rate().abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_trophy_bounds_are_inclusive() {
+    let play = JudgeCounts {
+        pgreat: 400,
+        great: 200,
+        bad: 50,
+        total_notes: 1000,
+        ..JudgeCounts::default()
+    };
+    // score rate 50%, miss rate 40% (50 BAD + 350 unjudged)
+    assert!(trophy("exact", 40.0, 50.0).is_achieved(&play));
+    assert!(!trophy("miss", 39.9, 50.0).is_achieved(&play));
+    assert!(!trophy("score", 40.0, 50.1).is_achieved(&play));
+}
+
+#[test]
+fn test_course_achieved_trophies() {
+    let course = CourseInfo {
+        name: "Course".to_string(),
+        constraint: Vec::new(),
+        trophy: vec![
+            trophy("bronzemedal", 7.5, 55.0),
+            trophy("silvermedal", 5.0, 70.0),
+            trophy("goldmedal", 2.5, 85.0),
+        ],
+        charts: Vec::new(),
+    };
+    let play = JudgeCounts {
+        pgreat: 2400,
+        great: 700,
+        good: 80,
+        bad: 20,
+        poor: 40,
+        empty_poor: 5,
+        total_notes: 3240,
+    };
+
+    let names: Vec<_> = course
+        .achieved_trophies(&play)
+        .into_iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(names, ["bronzemedal", "silvermedal"]);
+}
@@ -12,6 +12,12 @@ fn test_header_serialize_flattens_extra() {
         data_url: "charts.json".to_string(),
         course: vec![Vec::new()],
         level_order: vec!["0".to_string(), "1".to_string()],
+        tag: None,
+        mode: None,
+        last_update: None,
+        update_url: None,
+        tag_order: None,
+        comment: None,
         extra: {
             let mut m = BTreeMap::new();
             m.insert("extra_field".to_string(), serde_json::json!("extra_value"));
@@ -46,7 +52,7 @@ fn test_header_serialize_flattens_extra() {
 fn test_chart_item_serialize_flattens_extra() {
     let item = ChartItem {
         level: "1".to_string(),
-        md5: Some("md5hash".to_string()),
+        md5: "0123456789abcdef0123456789abcdef".parse().ok(),
         sha256: None,
         title: Some("Song Title".to_string()),
         subtitle: None,